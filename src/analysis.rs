@@ -0,0 +1,713 @@
+// src/analysis.rs
+//
+// Checks on assembled entities.  These run after geometry compilation, on
+// the parts where `resolve_offsets` puts them, and report what relations
+// alone can't catch:
+//
+//   overlaps — parts whose voxels land in the same cells.  `merge_parts`
+//              draws later parts over earlier ones, so without this pass
+//              the loser just silently loses voxels.
+//   floating — pieces of an entity with no connection to its ground, left
+//              by relation mistakes or carving.  `resolve floating = …`
+//              says whether to warn, fail, delete them or drop them.
+//   mass     — volume, mass, centre of mass and inertia from atom
+//              densities, for `moxi stats`.
+//   bounds   — `constraint Skull.height < Spine.height / 4` and friends,
+//              measured on the same figures.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::ast::{BinOp, CmpOp, ConstraintExpr, Expr, RelationKind, RelationStmt};
+use crate::error::MoxiError;
+use crate::geometry::{grid_center, merge_parts, CompiledEntity, CompiledPart};
+use crate::relation_resolver::{assemble_entity, mirror_copies, resolve_offsets};
+use crate::resolver::{FloatingPolicy, ResolvedAtom, ResolvedEntity, ResolvedScene};
+use crate::voxel::VoxelGrid;
+
+/// Atom id for overlap cells in `mark_overlaps`.  No atom has it, so it
+/// renders in the fallback magenta.
+pub const OVERLAP_ATOM: u16 = u16::MAX;
+
+/// One voxel position in entity space.
+pub type Cell = (i32, i32, i32);
+
+/// A part's filled cells with their atom ids.
+type PartCells = Vec<(Cell, u16)>;
+
+// ── Overlaps ───────────────────────────────────────────────────────────────
+
+/// Cells two parts of one entity both fill, in entity space.  `over` is
+/// drawn after `under`, so it keeps the cells.
+#[derive(Debug, Clone)]
+pub struct Overlap {
+    pub under: String,
+    pub over:  String,
+    pub cells: Vec<Cell>,
+}
+
+/// Every pair of parts (mirrored copies included) whose voxels meet,
+/// in drawing order.  Pairs that asked to overlap — `inside`, `surrounds`,
+/// `attach_to = center_of(…)` or an `overlap = n` qualifier between them —
+/// are left out.
+pub fn part_overlaps(parts: &[CompiledPart], relations: &[RelationStmt], voxel_size: f64) -> Vec<Overlap> {
+    let placed = part_cells(parts, relations, voxel_size);
+
+    // Cell → part that last drew it; (under, over) → shared cells
+    let mut owner: HashMap<Cell, usize> = HashMap::new();
+    let mut pairs: BTreeMap<(usize, usize), Vec<Cell>> = BTreeMap::new();
+    for (i, (_, cells)) in placed.iter().enumerate() {
+        for &(cell, _) in cells {
+            if let Some(j) = owner.insert(cell, i) {
+                pairs.entry((j, i)).or_default().push(cell);
+            }
+        }
+    }
+
+    let intended = |a: &str, b: &str| relations.iter().any(|r| {
+        let between = (r.subject.name == a && r.object.name == b) || (r.subject.name == b && r.object.name == a);
+        between && (matches!(r.predicate, RelationKind::Inside | RelationKind::Surrounds)
+            || r.args.iter().any(|arg| arg.key == "overlap"))
+    });
+    pairs.into_iter()
+        .filter(|((j, i), _)| !intended(&placed[*j].0, &placed[*i].0))
+        .map(|((j, i), cells)| Overlap { under: placed[j].0.clone(), over: placed[i].0.clone(), cells })
+        .collect()
+}
+
+/// Each part's filled cells in entity space, where `resolve_offsets` puts
+/// them, in drawing order (mirrored copies last).
+fn part_cells(parts: &[CompiledPart], relations: &[RelationStmt], voxel_size: f64) -> Vec<(String, PartCells)> {
+    let offsets = resolve_offsets(parts, relations, voxel_size);
+    let copies  = mirror_copies(parts, relations);
+    parts.iter().chain(&copies)
+        .map(|part| {
+            let off = offsets.get(&part.name).copied().unwrap_or_default();
+            let (gx, gy, gz) = grid_center(&part.grid);
+            let cells = part.grid.iter_filled()
+                .map(|(x, y, z, atom)| ((x as i32 - gx + off.dx, y as i32 - gy + off.dy, z as i32 - gz + off.dz), atom))
+                .collect();
+            (part.name.clone(), cells)
+        })
+        .collect()
+}
+
+/// Where the assembled grid's (0, 0, 0) is in entity space: `merge_parts`
+/// puts the lowest cell of any part there.
+fn grid_origin(placed: &[(String, PartCells)]) -> Cell {
+    let all = placed.iter().flat_map(|(_, cells)| cells).map(|(c, _)| c);
+    (
+        all.clone().map(|c| c.0).min().unwrap_or(0),
+        all.clone().map(|c| c.1).min().unwrap_or(0),
+        all.map(|c| c.2).min().unwrap_or(0),
+    )
+}
+
+/// Overlapping parts in every entity: a `ConstraintViolation` each in
+/// entities that declare `constraint no_overlap`, a warning each elsewhere.
+/// Returns `(errors, warnings)`.
+pub fn check_overlaps(compiled: &[CompiledEntity], scene: &ResolvedScene) -> (Vec<MoxiError>, Vec<String>) {
+    let mut errors   = Vec::new();
+    let mut warnings = Vec::new();
+    for (ent, resolved) in compiled.iter().zip(&scene.entities) {
+        let strict = resolved.constraints.iter()
+            .any(|c| matches!(&c.expr, ConstraintExpr::Rule(r) if r.name == "no_overlap"));
+        for o in part_overlaps(&ent.parts, &resolved.relations, scene.voxel_size) {
+            let description = format!(
+                "in '{}', '{}' and '{}' overlap by {} voxel(s) — '{}' is drawn over '{}'",
+                ent.name, o.under, o.over, o.cells.len(), o.over, o.under,
+            );
+            if strict {
+                errors.push(MoxiError::ConstraintViolation { description: format!("no_overlap: {description}") });
+            } else {
+                warnings.push(description);
+            }
+        }
+    }
+    (errors, warnings)
+}
+
+/// `assemble_entity` with every overlap cell drawn in `OVERLAP_ATOM`, to
+/// see where parts collide.
+pub fn mark_overlaps(parts: &[CompiledPart], relations: &[RelationStmt], voxel_size: f64) -> VoxelGrid {
+    let offsets = resolve_offsets(parts, relations, voxel_size);
+    let mut offsets_vec: Vec<_> = offsets.iter()
+        .map(|(n, o)| (n.clone(), (o.dx, o.dy, o.dz)))
+        .collect();
+    let mut all: Vec<CompiledPart> = parts.iter().cloned().chain(mirror_copies(parts, relations)).collect();
+
+    let cells: Vec<Cell> = part_overlaps(parts, relations, voxel_size)
+        .into_iter().flat_map(|o| o.cells).collect();
+    if let Some(marker) = cell_part(&cells) {
+        offsets_vec.push(("(overlaps)".to_string(), marker.1));
+        all.push(marker.0);
+    }
+    merge_parts(&all, &offsets_vec)
+}
+
+/// A part holding exactly `cells`, with the offset that puts them back
+/// where they were.
+fn cell_part(cells: &[Cell]) -> Option<(CompiledPart, (i32, i32, i32))> {
+    let lo = (
+        cells.iter().map(|c| c.0).min()?,
+        cells.iter().map(|c| c.1).min()?,
+        cells.iter().map(|c| c.2).min()?,
+    );
+    let hi = (
+        cells.iter().map(|c| c.0).max()?,
+        cells.iter().map(|c| c.1).max()?,
+        cells.iter().map(|c| c.2).max()?,
+    );
+    let mut grid = VoxelGrid::new((hi.0 - lo.0 + 1) as u32, (hi.1 - lo.1 + 1) as u32, (hi.2 - lo.2 + 1) as u32);
+    for &(x, y, z) in cells {
+        grid.set(x - lo.0, y - lo.1, z - lo.2, OVERLAP_ATOM);
+    }
+    let (gx, gy, gz) = grid_center(&grid);
+    let part = CompiledPart {
+        name:       "(overlaps)".to_string(),
+        grid,
+        atom_id:    OVERLAP_ATOM,
+        voxel_size: 1.0,
+        mirror_of:  None,
+    };
+    Some((part, (lo.0 + gx, lo.1 + gy, lo.2 + gz)))
+}
+
+// ── Floating pieces ────────────────────────────────────────────────────────
+
+/// The pieces of an assembled entity grid that don't reach its ground: the
+/// `ground` part if it names one, else the grid's lowest layer.  Cells are
+/// in grid coordinates.
+pub fn floating_pieces(
+    grid:       &VoxelGrid,
+    parts:      &[CompiledPart],
+    entity:     &ResolvedEntity,
+    voxel_size: f64,
+) -> Vec<Vec<Cell>> {
+    let grounding = &entity.grounding;
+    let mut ground: Vec<Cell> = Vec::new();
+    if let Some(name) = &grounding.ground {
+        let placed = part_cells(parts, &entity.relations, voxel_size);
+        let lo = grid_origin(&placed);
+        ground = placed.iter()
+            .filter(|(n, _)| n == name)
+            .flat_map(|(_, cells)| cells.iter().map(|(c, _)| (c.0 - lo.0, c.1 - lo.1, c.2 - lo.2)))
+            .collect();
+    }
+    let ground: HashSet<Cell> = ground.into_iter().collect();
+
+    grid.components(grounding.diagonal)
+        .into_iter()
+        .filter(|piece| if ground.is_empty() {
+            !piece.iter().any(|c| c.1 == 0)
+        } else {
+            !piece.iter().any(|c| ground.contains(c))
+        })
+        .collect()
+}
+
+/// Apply a `remove` or `drop` policy to an entity's floating pieces in
+/// `grid`.  `remove` deletes the pieces no bigger than its limit and leaves
+/// the rest; `drop` lowers each piece, lowest first, until it rests on
+/// another voxel or the bottom of the grid.
+pub fn settle_floating(grid: &mut VoxelGrid, pieces: &[Vec<Cell>], policy: FloatingPolicy) {
+    if !matches!(policy, FloatingPolicy::Remove { .. } | FloatingPolicy::Drop) {
+        return;
+    }
+    let mut pieces: Vec<&Vec<Cell>> = pieces.iter().collect();
+    pieces.sort_by_key(|p| p.iter().map(|c| c.1).min());
+    for piece in pieces {
+        if let FloatingPolicy::Remove { max } = policy {
+            if piece.len() <= max {
+                for &(x, y, z) in piece {
+                    grid.set(x, y, z, 0);
+                }
+            }
+            continue;
+        }
+        let atoms: Vec<u16> = piece.iter().map(|&(x, y, z)| grid.get(x, y, z)).collect();
+        for &(x, y, z) in piece {
+            grid.set(x, y, z, 0);
+        }
+        let fall = piece.iter()
+            .map(|&(x, y, z)| (0..y).rev().take_while(|&below| grid.get(x, below, z) == 0).count() as i32)
+            .min()
+            .unwrap_or(0);
+        for (&(x, y, z), &atom) in piece.iter().zip(&atoms) {
+            grid.set(x, y - fall, z, atom);
+        }
+    }
+}
+
+/// `settle_floating` for an assembled entity, under its own policy.
+pub fn settle_entity(mut grid: VoxelGrid, parts: &[CompiledPart], entity: &ResolvedEntity, voxel_size: f64) -> VoxelGrid {
+    if matches!(entity.grounding.policy, FloatingPolicy::Remove { .. } | FloatingPolicy::Drop) {
+        let pieces = floating_pieces(&grid, parts, entity, voxel_size);
+        settle_floating(&mut grid, &pieces, entity.grounding.policy);
+    }
+    grid
+}
+
+/// Floating pieces found in one entity, and the policy that handles them.
+#[derive(Debug, Clone)]
+pub struct FloatingReport {
+    pub entity: String,
+    pub pieces: usize,
+    pub voxels: usize,
+    pub policy: FloatingPolicy,
+    /// Pieces too big for `remove` to delete, left in place.
+    pub kept:   usize,
+    /// At what stage they were found, e.g. " after carving"; empty for the
+    /// check after compilation.
+    pub when:   String,
+}
+
+impl FloatingReport {
+    /// The report for `pieces` of entity `name`, or `None` if there are none.
+    pub fn new(name: &str, pieces: &[Vec<Cell>], policy: FloatingPolicy, when: &str) -> Option<Self> {
+        if pieces.is_empty() {
+            return None;
+        }
+        let kept = match policy {
+            FloatingPolicy::Remove { max } => pieces.iter().filter(|p| p.len() > max).count(),
+            _ => 0,
+        };
+        Some(Self {
+            entity: name.to_string(),
+            pieces: pieces.len(),
+            voxels: pieces.iter().map(Vec::len).sum(),
+            policy,
+            kept,
+            when:   when.to_string(),
+        })
+    }
+
+    /// The `ConstraintViolation` for an entity under `resolve floating = error`.
+    pub fn violation(&self) -> Option<MoxiError> {
+        (self.policy == FloatingPolicy::Error)
+            .then(|| MoxiError::ConstraintViolation { description: format!("floating: {self}") })
+    }
+}
+
+impl std::fmt::Display for FloatingReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "'{}' has {} floating piece(s), {} voxel(s){}", self.entity, self.pieces, self.voxels, self.when)
+    }
+}
+
+/// Every entity with floating pieces, as assembled after compilation.
+pub fn check_floating(compiled: &[CompiledEntity], scene: &ResolvedScene) -> Vec<FloatingReport> {
+    compiled.iter().zip(&scene.entities)
+        .filter_map(|(ent, resolved)| {
+            let grid = assemble_entity(&ent.parts, &resolved.relations, scene.voxel_size);
+            let pieces = floating_pieces(&grid, &ent.parts, resolved, scene.voxel_size);
+            FloatingReport::new(&ent.name, &pieces, resolved.grounding.policy, "")
+        })
+        .collect()
+}
+
+// ── Mass properties ────────────────────────────────────────────────────────
+
+/// Physical properties of a set of voxels, in world units.  Each voxel
+/// weighs its atom's `density` × `voxel_size`³.  Positions are measured
+/// from the low corner of the entity's assembled grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MassProps {
+    pub voxels:         usize,
+    pub volume:         f64,
+    pub mass:           f64,
+    pub center_of_mass: (f64, f64, f64),
+    /// Inertia tensor about the centre of mass, each voxel a solid cube.
+    pub inertia:        [[f64; 3]; 3],
+    /// Low and high corners of the bounding box.
+    pub bounds:         ((f64, f64, f64), (f64, f64, f64)),
+}
+
+/// Mass properties of an entity as assembled, and of each part on its own
+/// (parts that overlap both count the shared voxels).
+#[derive(Debug, Clone)]
+pub struct EntityStats {
+    pub name:  String,
+    pub total: MassProps,
+    pub parts: Vec<(String, MassProps)>,
+}
+
+/// Mass properties of `cells` (grid coordinates, with their atom ids).
+pub fn mass_props(cells: impl IntoIterator<Item = (Cell, u16)>, atoms: &[ResolvedAtom], voxel_size: f64) -> MassProps {
+    let cube = voxel_size.powi(3);
+    let mut voxels = 0;
+    let mut mass = 0.0;
+    let mut first  = [0.0; 3];        // Σ m·p
+    let mut second = [[0.0; 3]; 3];   // Σ m·pᵢ·pⱼ
+    let mut lo = [i32::MAX; 3];
+    let mut hi = [i32::MIN; 3];
+    for ((x, y, z), atom) in cells {
+        for (i, v) in [x, y, z].into_iter().enumerate() {
+            lo[i] = lo[i].min(v);
+            hi[i] = hi[i].max(v);
+        }
+        let density = atoms.get((atom as usize).wrapping_sub(1)).map(|a| a.density).unwrap_or(1.0);
+        let m = density * cube;
+        // Voxel centres, in world units
+        let p = [(x as f64 + 0.5) * voxel_size, (y as f64 + 0.5) * voxel_size, (z as f64 + 0.5) * voxel_size];
+        voxels += 1;
+        mass += m;
+        for i in 0..3 {
+            first[i] += m * p[i];
+            for j in 0..3 {
+                second[i][j] += m * p[i] * p[j];
+            }
+        }
+    }
+    if voxels == 0 {
+        let zero = (0.0, 0.0, 0.0);
+        return MassProps { voxels, volume: 0.0, mass, center_of_mass: zero, inertia: [[0.0; 3]; 3], bounds: (zero, zero) };
+    }
+    let corner = |c: [i32; 3], add: i32| {
+        let at = |i: usize| (c[i] + add) as f64 * voxel_size;
+        (at(0), at(1), at(2))
+    };
+    let bounds = (corner(lo, 0), corner(hi, 1));
+
+    // Second moments about the centre of mass, plus each cube's own m·s²/6
+    let c = [first[0] / mass, first[1] / mass, first[2] / mass];
+    let central = |i: usize, j: usize| second[i][j] - mass * c[i] * c[j];
+    let own = mass * voxel_size * voxel_size / 6.0;
+    let mut inertia = [[0.0; 3]; 3];
+    for (i, row) in inertia.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = if i == j {
+                central((i + 1) % 3, (i + 1) % 3) + central((i + 2) % 3, (i + 2) % 3) + own
+            } else {
+                -central(i, j)
+            };
+            // Symmetric shapes cancel to rounding noise off the diagonal
+            if value.abs() < 1e-9 * mass.max(1.0) {
+                *value = 0.0;
+            }
+        }
+    }
+    MassProps {
+        voxels,
+        volume: voxels as f64 * cube,
+        mass,
+        center_of_mass: (c[0], c[1], c[2]),
+        inertia,
+        bounds,
+    }
+}
+
+/// Mass properties of one compiled entity and its parts.
+pub fn entity_stats(ent: &CompiledEntity, resolved: &ResolvedEntity, scene: &ResolvedScene) -> EntityStats {
+    let vs = scene.voxel_size;
+    let grid = settle_entity(assemble_entity(&ent.parts, &resolved.relations, vs), &ent.parts, resolved, vs);
+    let total = mass_props(
+        grid.iter_filled().map(|(x, y, z, atom)| ((x as i32, y as i32, z as i32), atom)),
+        &scene.atoms,
+        vs,
+    );
+
+    // Parts in the grid's frame
+    let placed = part_cells(&ent.parts, &resolved.relations, vs);
+    let lo = grid_origin(&placed);
+    let parts = placed.iter()
+        .map(|(name, cells)| {
+            let cells = cells.iter().map(|&(c, atom)| ((c.0 - lo.0, c.1 - lo.1, c.2 - lo.2), atom));
+            (name.clone(), mass_props(cells, &scene.atoms, vs))
+        })
+        .collect();
+
+    EntityStats { name: ent.name.clone(), total, parts }
+}
+
+// ── Constraint bounds ──────────────────────────────────────────────────────
+
+/// Properties a bound can measure: `Skull.height`, `Skeleton.width`, or a
+/// bare name for the whole entity (`mass < 40`).  Lengths and positions
+/// are in world units, positions from the low corner of the entity's grid;
+/// `center_*` is the bounding box centre.
+pub const MEASURES: &[&str] = &[
+    "width", "height", "depth", "volume", "mass", "voxels", "center_x", "center_y", "center_z",
+];
+
+/// Check every `constraint <expr> <op> <expr>` bound against the assembled
+/// entity, with both sides' values in the message when it fails, or the
+/// side that has no value.
+pub fn check_bounds(compiled: &[CompiledEntity], scene: &ResolvedScene) -> Vec<MoxiError> {
+    let mut errors = Vec::new();
+    for (ent, resolved) in compiled.iter().zip(&scene.entities) {
+        let bounds: Vec<(&Expr, &CmpOp, &Expr)> = resolved.constraints.iter()
+            .filter_map(|c| match &c.expr {
+                ConstraintExpr::Bound { lhs, op, rhs } => Some((lhs, op, rhs)),
+                _ => None,
+            })
+            .collect();
+        if bounds.is_empty() { continue; }
+
+        let stats = entity_stats(ent, resolved, scene);
+        for (lhs, op, rhs) in bounds {
+            let bound = format!("{} {} {}", expr_text(lhs), cmp_symbol(op), expr_text(rhs));
+            let (measured, limit) = match (measure(lhs, &stats), measure(rhs, &stats)) {
+                (Some(m), Some(l)) => (m, l),
+                (m, _) => {
+                    let side = if m.is_none() { lhs } else { rhs };
+                    errors.push(MoxiError::ConstraintViolation {
+                        description: format!("in '{}', {bound}: cannot measure {}", ent.name, expr_text(side)),
+                    });
+                    continue;
+                }
+            };
+            if !compare(op, measured, limit) {
+                errors.push(MoxiError::ConstraintViolation {
+                    description: format!(
+                        "in '{}', {bound}: measured {measured:.2}, expected {} {limit:.2}",
+                        ent.name, cmp_symbol(op),
+                    ),
+                });
+            }
+        }
+    }
+    errors
+}
+
+/// The value of one side of a bound; `None` for a division by zero or
+/// anything the resolver would have rejected.
+fn measure(e: &Expr, stats: &EntityStats) -> Option<f64> {
+    let props = |owner: &str| if owner == stats.name {
+        Some(&stats.total)
+    } else {
+        stats.parts.iter().find(|(name, _)| name == owner).map(|(_, m)| m)
+    };
+    let centre = |m: &MassProps| {
+        let ((x0, y0, z0), (x1, y1, z1)) = m.bounds;
+        ((x0 + x1) / 2.0, (y0 + y1) / 2.0, (z0 + z1) / 2.0)
+    };
+    let property = |m: &MassProps, name: &str| {
+        let ((x0, y0, z0), (x1, y1, z1)) = m.bounds;
+        Some(match name {
+            "width"    => x1 - x0,
+            "height"   => y1 - y0,
+            "depth"    => z1 - z0,
+            "volume"   => m.volume,
+            "mass"     => m.mass,
+            "voxels"   => m.voxels as f64,
+            "center_x" => centre(m).0,
+            "center_y" => centre(m).1,
+            "center_z" => centre(m).2,
+            _          => return None,
+        })
+    };
+    match e {
+        Expr::Int(n)   => Some(*n as f64),
+        Expr::Float(f) => Some(*f),
+        Expr::Ident(i) => property(&stats.total, &i.name),
+        Expr::Property { owner, name } => property(props(&owner.name)?, &name.name),
+        Expr::Call { name, positional, .. } if name == "distance" => {
+            let at = |p: Option<&Expr>| match p {
+                Some(Expr::Ident(i)) => props(&i.name).map(centre),
+                _ => None,
+            };
+            let (a, b) = (at(positional.first())?, at(positional.get(1))?);
+            Some(((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)).sqrt())
+        }
+        Expr::BinOp { op, lhs, rhs } => {
+            let (l, r) = (measure(lhs, stats)?, measure(rhs, stats)?);
+            match op {
+                BinOp::Add => Some(l + r),
+                BinOp::Sub => Some(l - r),
+                BinOp::Mul => Some(l * r),
+                BinOp::Div if r != 0.0 => Some(l / r),
+                _          => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// A bound's side as it was written, for messages.
+fn expr_text(e: &Expr) -> String {
+    match e {
+        Expr::Int(n)   => n.to_string(),
+        Expr::Float(f) => f.to_string(),
+        Expr::Ident(i) => i.name.clone(),
+        Expr::Property { owner, name } => format!("{}.{}", owner.name, name.name),
+        Expr::Call { name, positional, .. } => {
+            let args: Vec<String> = positional.iter().map(expr_text).collect();
+            format!("{name}({})", args.join(", "))
+        }
+        Expr::BinOp { op, lhs, rhs } => {
+            let symbol = match op {
+                BinOp::Add => "+",
+                BinOp::Sub => "-",
+                BinOp::Mul => "*",
+                BinOp::Div => "/",
+                _          => "?",
+            };
+            // Parenthesise sums inside products so the text reads back the same
+            let side = |e: &Expr| match e {
+                Expr::BinOp { op: BinOp::Add | BinOp::Sub, .. } if matches!(op, BinOp::Mul | BinOp::Div) =>
+                    format!("({})", expr_text(e)),
+                _ => expr_text(e),
+            };
+            format!("{} {symbol} {}", side(lhs), side(rhs))
+        }
+        _ => "…".to_string(),
+    }
+}
+
+fn compare(op: &CmpOp, a: f64, b: f64) -> bool {
+    match op {
+        CmpOp::Lt   => a < b,
+        CmpOp::Gt   => a > b,
+        CmpOp::LtEq => a <= b,
+        CmpOp::GtEq => a >= b,
+        CmpOp::Eq   => a == b,
+        CmpOp::Neq  => a != b,
+    }
+}
+
+fn cmp_symbol(op: &CmpOp) -> &'static str {
+    match op {
+        CmpOp::Lt   => "<",
+        CmpOp::Gt   => ">",
+        CmpOp::LtEq => "<=",
+        CmpOp::GtEq => ">=",
+        CmpOp::Eq   => "==",
+        CmpOp::Neq  => "!=",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::compile;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::resolver::Resolver;
+
+    /// Resolve and compile `src` after a prelude declaring material `M`.
+    fn build(src: &str) -> (ResolvedScene, Vec<CompiledEntity>) {
+        let src = format!("
+            atom A {{ color = gray }}
+            material M {{ color = gray, voxel_atom = A }}
+            {src}
+        ");
+        let (tokens, lex_errors) = Lexer::new(&src).tokenize();
+        let (doc, parse_errors) = Parser::new(tokens).parse();
+        let (scene, errors) = Resolver::new().resolve(doc);
+        assert!(lex_errors.is_empty() && parse_errors.is_empty() && errors.is_empty(),
+            "{lex_errors:?} {parse_errors:?} {errors:?}");
+        let (compiled, _) = compile(&scene);
+        (scene, compiled)
+    }
+
+    /// Three parts centred on one point: `Core` is meant to sit inside
+    /// `Base`, `Flag` isn't.
+    const TOWER: &str = "
+        part Base { shape = box(width=2, height=2, depth=2), material = M }
+        part Core { shape = box(width=0, height=0, depth=0), material = M }
+        part Flag { shape = box(width=0, height=0, depth=0), material = M }
+        relation { Core inside Base }
+    ";
+
+    #[test]
+    fn overlaps_skip_pairs_that_asked_to_overlap() {
+        let (scene, compiled) = build(&format!("entity Tower {{ {TOWER} }}"));
+        let overlaps = part_overlaps(&compiled[0].parts, &scene.entities[0].relations, 1.0);
+        let [o] = &overlaps[..] else { panic!("{overlaps:?}") };
+        assert_eq!((o.under.as_str(), o.over.as_str(), o.cells.len()), ("Core", "Flag", 1));
+
+        let (errors, warnings) = check_overlaps(&compiled, &scene);
+        assert!(errors.is_empty() && warnings.len() == 1, "{errors:?} {warnings:?}");
+    }
+
+    #[test]
+    fn no_overlap_turns_the_warning_into_an_error() {
+        let (scene, compiled) = build(&format!("entity Tower {{ {TOWER} constraint no_overlap }}"));
+        let (errors, warnings) = check_overlaps(&compiled, &scene);
+        assert!(warnings.is_empty());
+        assert!(matches!(&errors[..], [MoxiError::ConstraintViolation { description }]
+            if description.starts_with("no_overlap: in 'Tower', 'Core' and 'Flag' overlap by 1 voxel(s)")), "{errors:?}");
+    }
+
+    #[test]
+    fn floating_pieces_are_kept_removed_or_dropped() {
+        let (scene, compiled) = build("entity Lamp {
+            part Post  { shape = box(width=2, height=2, depth=2), material = M }
+            part Light { shape = box(width=0, height=0, depth=0), material = M }
+            relation { Light above Post gap=2 }
+            resolve floating = remove(max = 1)
+        }");
+        let (ent, resolved) = (&compiled[0], &scene.entities[0]);
+        assert_eq!(resolved.grounding.policy, FloatingPolicy::Remove { max: 1 });
+        let grid = assemble_entity(&ent.parts, &resolved.relations, 1.0);
+        let pieces = floating_pieces(&grid, &ent.parts, resolved, 1.0);
+        assert_eq!(pieces, [vec![(1, 5, 1)]]);
+
+        let report = FloatingReport::new("Lamp", &pieces, resolved.grounding.policy, "").unwrap();
+        assert_eq!((report.pieces, report.voxels, report.kept), (1, 1, 0));
+        assert_eq!(check_floating(&compiled, &scene).len(), 1);
+
+        // Too big for this `remove`, so it stays
+        let mut kept = grid.clone();
+        settle_floating(&mut kept, &pieces, FloatingPolicy::Remove { max: 0 });
+        assert_eq!(kept.get(1, 5, 1), grid.get(1, 5, 1));
+
+        let removed = settle_entity(grid.clone(), &ent.parts, resolved, 1.0);
+        assert_eq!((removed.get(1, 5, 1), removed.filled_count()), (0, 27));
+
+        let mut dropped = grid.clone();
+        settle_floating(&mut dropped, &pieces, FloatingPolicy::Drop);
+        assert_eq!((dropped.get(1, 5, 1), dropped.get(1, 3, 1)), (0, grid.get(1, 5, 1)));
+    }
+
+    #[test]
+    fn mass_props_weigh_voxels_by_density() {
+        let atom = |density| ResolvedAtom { name: "A".into(), color: "gray".into(), density };
+        let atoms = [atom(1.0), atom(3.0)];
+
+        // One light and one heavy voxel side by side, half a unit each
+        let m = mass_props([((0, 0, 0), 1), ((1, 0, 0), 2)], &atoms, 0.5);
+        assert_eq!((m.voxels, m.volume, m.mass), (2, 0.25, 0.5));
+        assert_eq!(m.center_of_mass, (0.625, 0.25, 0.25));
+        assert_eq!(m.bounds, ((0.0, 0.0, 0.0), (1.0, 0.5, 0.5)));
+
+        // A lone cube: m·s²/6 about every axis, nothing off the diagonal
+        let m = mass_props([((4, 0, 0), 2)], &atoms, 1.0);
+        assert_eq!(m.inertia, [[0.5, 0.0, 0.0], [0.0, 0.5, 0.0], [0.0, 0.0, 0.5]]);
+    }
+
+    #[test]
+    fn entity_stats_cover_the_whole_and_each_part() {
+        let (scene, compiled) = build("
+            atom Lead { color = gray, density = 11 }
+            material Heavy { color = gray, voxel_atom = Lead }
+            entity Weight {
+                part Base { shape = box(width=2, height=2, depth=2), material = M }
+                part Knob { shape = box(width=0, height=0, depth=0), material = Heavy }
+                relation { Knob above Base }
+            }
+        ");
+        let stats = entity_stats(&compiled[0], &scene.entities[0], &scene);
+        assert_eq!((stats.total.voxels, stats.total.mass), (28, 27.0 + 11.0));
+        let names: Vec<&str> = stats.parts.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["Base", "Knob"]);
+        assert_eq!(stats.parts[1].1.center_of_mass, (1.5, 3.5, 1.5));
+    }
+
+    #[test]
+    fn bounds_measure_parts_and_report_both_sides() {
+        let (scene, compiled) = build("entity Weight {
+            part Base { shape = box(width=2, height=2, depth=2), material = M }
+            part Knob { shape = box(width=0, height=0, depth=0), material = M }
+            relation { Knob above Base }
+            constraint voxels == 28
+            constraint distance(Knob, Base) > 1.5
+            constraint Knob.height < Base.height / 4
+            constraint Knob.height < Base.height / (Knob.width - 1)
+        }");
+        let errors: Vec<String> = check_bounds(&compiled, &scene).iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(errors[0].contains("in 'Weight', Knob.height < Base.height / 4: measured 1.00, expected < 0.75"));
+        assert!(errors[1].contains("cannot measure Base.height / (Knob.width - 1)"), "{errors:?}");
+    }
+}
@@ -17,6 +17,7 @@ pub struct Document {
 
 /// Everything that can appear at the top level of a Moxi file.
 #[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum TopLevel {
     // v1 assembly layer
     AtomDecl(AtomDecl),
@@ -26,9 +27,13 @@ pub enum TopLevel {
     MaterialDecl(MaterialDecl),
     EntityDecl(EntityDecl),
     GeneratorDecl(GeneratorDecl),
+    CarveDecl(CarveDecl),
     WorldDecl(WorldDecl),
+    LayoutDecl(LayoutDecl),
+    DetailDecl(DetailDecl),
 
     // Statements
+    PlaceStmt(PlaceStmt),
     PrintStmt(PrintStmt),
     RefineStmt(RefineStmt),
 }
@@ -92,17 +97,31 @@ pub struct EntityDecl {
 pub struct PartDecl {
     pub name: Ident,
     pub shape: Option<ShapeExpr>,
+    /// `part RightArm mirror_of LeftArm axis=x` — a mirrored copy.
+    pub mirror_of: Option<MirrorSpec>,
     pub material: Option<Ident>,
-    pub anchor: Option<Ident>,
+    /// `anchor = base | center | top | (x, y, z)` — the point of this part
+    /// that lands on its `attach_to` point.
+    pub anchor: Option<Prop>,
     pub attach_to: Option<AttachSpec>,
     pub span: Span,
 }
 
-/// `attach_to = top_of(Trunk)`
+/// `mirror_of LeftArm axis=x` — the axis defaults to x.
+#[derive(Debug, Clone)]
+pub struct MirrorSpec {
+    pub source: Ident,
+    pub axis: Option<Ident>,
+}
+
+/// `attach_to = top_of(Trunk)`, `attach_to = surface_at(Trunk, 0.3, 0.7)`
 #[derive(Debug, Clone)]
 pub struct AttachSpec {
     pub anchor_fn: String,
     pub target: Ident,
+    /// Arguments after the target: `surface_at`'s (u, v).
+    pub args: Vec<Expr>,
+    pub span: Span,
 }
 
 // ── Shape expressions ──────────────────────────────────────────────────────
@@ -130,13 +149,16 @@ pub struct NamedArg {
 
 // ── Relation and constraint statements ────────────────────────────────────
 
-/// One spatial relationship between two named parts.
+/// One spatial relationship between two named parts (or, in a `layout`
+/// block, two entities).
 #[derive(Debug, Clone)]
 pub struct RelationStmt {
     pub subject: Ident,
     pub predicate: RelationKind,
     pub object: Ident,
     pub qualifiers: Vec<Ident>,
+    /// `key = value` qualifiers: `gap = 10`.
+    pub args: Vec<Prop>,
     pub span: Span,
 }
 
@@ -155,6 +177,10 @@ pub enum RelationKind {
     AttachedTo,
     Touch,
     Surrounds,
+    /// `Castle on Hill` — rests on the object's surface (layout only).
+    On,
+    /// `Bridge across River` — spans a river, path or entity (layout only).
+    Across,
 }
 
 /// A hard rule that the resolved geometry must satisfy.
@@ -164,11 +190,15 @@ pub struct ConstraintStmt {
     pub span: Span,
 }
 
-/// Constraints are either spatial relations or numeric bounds.
+/// Constraints are spatial relations, numeric bounds or named rules.
 #[derive(Debug, Clone)]
 pub enum ConstraintExpr {
     Relation(RelationStmt),
-    Bound { name: Ident, op: CmpOp, value: Expr },
+    /// `mass < 40`, `Skull.height < Spine.height / 4`,
+    /// `distance(Skull, Pelvis) > 20`
+    Bound { lhs: Expr, op: CmpOp, rhs: Expr },
+    /// `constraint no_overlap` — a check on the whole entity.
+    Rule(Ident),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -180,13 +210,39 @@ pub enum CmpOp { Lt, Gt, LtEq, GtEq, Eq, Neq }
 #[derive(Debug, Clone)]
 pub struct GeneratorDecl {
     pub name: Ident,
-    pub scatter_target: Ident,
+    /// `scatter Tree`, or a variant pool `scatter [Tree, Bush, Rock]`
+    pub scatter_targets: Vec<Ident>,
+    pub props: Vec<Prop>,
+    pub span: Span,
+}
+
+// ── Rivers and paths ──────────────────────────────────────────────────────
+
+/// `river Main { from = (10, -4), to = sea, width = 3, depth = 2, material = Ocean }`
+#[derive(Debug, Clone)]
+pub struct CarveDecl {
+    pub kind: CarveKind,
+    pub name: Ident,
     pub props: Vec<Prop>,
     pub span: Span,
 }
 
+/// Rivers run downhill and fill their channel; paths pave the cheapest route.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CarveKind {
+    River,
+    Path,
+}
+
 // ── World ──────────────────────────────────────────────────────────────────
 
+/// `layout { Castle on Hill, Village left_of Castle gap=10, Bridge across River }`
+#[derive(Debug, Clone)]
+pub struct LayoutDecl {
+    pub relations: Vec<RelationStmt>,
+    pub span: Span,
+}
+
 /// `world TropicalIsland { … }`
 #[derive(Debug, Clone)]
 pub struct WorldDecl {
@@ -222,10 +278,13 @@ pub struct WaterBlock {
     pub depth_material: Option<Ident>,
 }
 
-/// `resolve voxel_size = 1.0`
-#[derive(Debug, Clone)]
+/// `resolve voxel_size = 1.0`.  Several `resolve` lines merge into one.
+#[derive(Debug, Clone, Default)]
 pub struct ResolveOpts {
-    pub voxel_size: f64,
+    pub voxel_size: Option<f64>,
+    /// Other settings: `resolve floating = drop`, `resolve connectivity = 26`,
+    /// `resolve ground = Pelvis`.
+    pub props: Vec<Prop>,
 }
 
 // ── Statements ─────────────────────────────────────────────────────────────
@@ -238,6 +297,17 @@ pub struct PrintStmt {
     pub span: Span,
 }
 
+/// `place Lighthouse at (30, 0, -12) rotate = 90 on = terrain`, or
+/// `translate Lighthouse (30, 0, -12)`.  Each statement is one instance.
+#[derive(Debug, Clone)]
+pub struct PlaceStmt {
+    pub target: Ident,
+    /// `(x, y, z)` in world coordinates.
+    pub at: Expr,
+    pub props: Vec<Prop>,
+    pub span: Span,
+}
+
 /// `refine HumanBody.Chest detail=medium`
 #[derive(Debug, Clone)]
 pub struct RefineStmt {
@@ -246,7 +316,15 @@ pub struct RefineStmt {
     pub span: Span,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// `detail { sketch = 1, low = 1, medium = 0.5, high = 0.25 }` — the voxel
+/// size multiplier of each detail level.
+#[derive(Debug, Clone)]
+pub struct DetailDecl {
+    pub props: Vec<Prop>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DetailLevel {
     Sketch,
     Low,
@@ -275,10 +353,16 @@ pub enum Expr {
     BinOp { op: BinOp, lhs: Box<Expr>, rhs: Box<Expr> },
     /// `not x`
     Not(Box<Expr>),
-    /// `noise(scale=0.1)`
-    Call { name: String, args: Vec<NamedArg> },
+    /// `noise(scale=0.1)`, `distance_to(Main)`
+    Call { name: String, positional: Vec<Expr>, args: Vec<NamedArg> },
+    /// `Skull.height` — a measured property of a part or entity
+    Property { owner: Ident, name: Ident },
     /// `[Tree, Leaf]`
     List(Vec<Expr>),
+    /// `(10, -4)`
+    Tuple(Vec<Expr>),
+    /// `0.8..1.2`
+    Range { lo: Box<Expr>, hi: Box<Expr> },
 }
 
 #[derive(Debug, Clone, PartialEq)]
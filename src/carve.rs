@@ -0,0 +1,322 @@
+// src/carve.rs
+//
+// The carve pass runs after the terrain grid is assembled and BEFORE the
+// generator pass.  Each `river` / `path` declaration traces a route over the
+// terrain's elevation map and cuts it into the grid:
+//
+//   river — least-cost route that strongly prefers going downhill, ending at
+//           the sea (or a given point).  The channel is sunk one voxel below
+//           its banks and filled with the river material.
+//   path  — least-cost route between two points that avoids steep climbs.
+//           The surface along the route is paved with the path material.
+//
+// Routes are returned so generators can query `distance_to(<name>)`, along
+// with a warning for each route that could not be carved.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::ast::CarveKind;
+use crate::generator::{build_elevation_map, Rect, SpatialHash};
+use crate::resolver::{CarveTarget, ResolvedCarve, ResolvedScene};
+use crate::voxel::VoxelGrid;
+
+// ── Public types ───────────────────────────────────────────────────────────
+
+/// The centre line of one carved river or path, in world (x, z) columns —
+/// the same space `run_generators` samples in.
+#[derive(Debug, Clone)]
+pub struct CarvedRoute {
+    pub name:  String,
+    pub kind:  CarveKind,
+    pub cells: Vec<(i32, i32)>,
+    /// `cells` bucketed by column, so `distance_to` only looks nearby.
+    index:     SpatialHash,
+}
+
+/// Bucket size of a route's spatial hash, in voxels.
+const ROUTE_BUCKET: i32 = 16;
+
+impl CarvedRoute {
+    pub fn new(name: String, kind: CarveKind, cells: Vec<(i32, i32)>) -> Self {
+        let mut index = SpatialHash::new(ROUTE_BUCKET);
+        for (i, &(x, z)) in cells.iter().enumerate() {
+            index.insert(i, Rect::column(x, z));
+        }
+        Self { name, kind, cells, index }
+    }
+
+    /// Horizontal distance from (x, z) to the nearest cell on the route.
+    ///
+    /// Searches a square around (x, z) that doubles until the nearest cell
+    /// in it is no further than its half-width — nothing outside can be
+    /// nearer — or it holds every cell.
+    pub fn distance_to(&self, x: i32, z: i32) -> f64 {
+        let mut reach = ROUTE_BUCKET;
+        loop {
+            let near = self.index.query(Rect::column(x, z).expand(reach));
+            let best = near.iter()
+                .map(|&i| {
+                    let (cx, cz) = self.cells[i];
+                    let dx = (x - cx) as f64;
+                    let dz = (z - cz) as f64;
+                    (dx*dx + dz*dz).sqrt()
+                })
+                .fold(f64::INFINITY, f64::min);
+            if best <= reach as f64 || near.len() == self.cells.len() {
+                return best;
+            }
+            reach *= 2;
+        }
+    }
+}
+
+// ── Entry point ────────────────────────────────────────────────────────────
+
+/// Trace and carve every river and path in `scene` into `terrain_grid`.
+///
+/// `origin` is the world (x, z) position of grid column (0, 0); `from` /
+/// `to` are world columns, already in voxels.
+///
+/// Returns every route, in declaration order, and a warning for each one
+/// that starts off the terrain or never reaches its destination (those
+/// routes have no cells).
+pub fn carve_routes(
+    terrain_grid: &mut VoxelGrid,
+    origin:       (i32, i32),
+    scene:        &ResolvedScene,
+) -> (Vec<CarvedRoute>, Vec<String>) {
+    let mut routes   = Vec::new();
+    let mut warnings = Vec::new();
+
+    for carve in &scene.carves {
+        // Each carve sees the terrain as left by the previous ones, so a path
+        // can cross a river and a second river can join the first.
+        let elev_map = build_elevation_map(terrain_grid);
+        let start = (carve.from.0 - origin.0, carve.from.1 - origin.1);
+
+        if !elev_map.contains_key(&start) {
+            warnings.push(format!("{} '{}' starts off the terrain at {:?}",
+                kind_label(carve.kind), carve.name, carve.from));
+            routes.push(CarvedRoute::new(carve.name.clone(), carve.kind, Vec::new()));
+            continue;
+        }
+
+        let cells = trace_route(carve, start, origin, &elev_map);
+        if cells.is_empty() {
+            warnings.push(format!("{} '{}' could not reach its destination",
+                kind_label(carve.kind), carve.name));
+        }
+
+        let atom_id = scene.materials.get(carve.material_index)
+            .map(|m| m.atom_index as u16 + 1)
+            .unwrap_or(1);
+        carve_channel(terrain_grid, &cells, &elev_map, carve, atom_id);
+
+        let cells = cells.into_iter().map(|(x, z)| (x + origin.0, z + origin.1)).collect();
+        routes.push(CarvedRoute::new(carve.name.clone(), carve.kind, cells));
+    }
+
+    (routes, warnings)
+}
+
+// ── Route tracing ──────────────────────────────────────────────────────────
+//
+// Dijkstra over terrain columns with 8-neighbour steps.  Step cost is the
+// horizontal distance scaled by how much the step climbs:
+//
+//   river:  d * (1 + 50 * uphill)     — will climb only to escape a pit
+//   path:   d * (1 +  4 * |dh|)       — avoids steep ground either way
+
+const NEIGHBOURS: [(i32, i32); 8] = [
+    (1, 0), (-1, 0), (0, 1), (0, -1),
+    (1, 1), (1, -1), (-1, 1), (-1, -1),
+];
+
+fn trace_route(
+    carve:    &ResolvedCarve,
+    start:    (i32, i32),
+    origin:   (i32, i32),
+    elev_map: &HashMap<(i32, i32), i32>,
+) -> Vec<(i32, i32)> {
+    let goal = match carve.to {
+        CarveTarget::Point(x, z) => Some((x - origin.0, z - origin.1)),
+        CarveTarget::Sea         => None,
+    };
+    let is_goal = |cell: (i32, i32)| match goal {
+        Some(g) => cell == g,
+        None => {
+            let h = elev_map[&cell];
+            h <= carve.sea_level
+                || NEIGHBOURS.iter().any(|&(dx, dz)| !elev_map.contains_key(&(cell.0 + dx, cell.1 + dz)))
+        }
+    };
+
+    // Costs are kept in fixed point (1/1000 voxel) so they can live in a heap.
+    let mut best: HashMap<(i32, i32), u64>        = HashMap::new();
+    let mut prev: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+    best.insert(start, 0);
+    heap.push(Reverse((0u64, start)));
+
+    while let Some(Reverse((cost, cell))) = heap.pop() {
+        if cost > best[&cell] { continue; }
+        if is_goal(cell) {
+            let mut path = vec![cell];
+            let mut cur = cell;
+            while let Some(&p) = prev.get(&cur) {
+                path.push(p);
+                cur = p;
+            }
+            path.reverse();
+            return path;
+        }
+
+        let h = elev_map[&cell] as f64;
+        for &(dx, dz) in &NEIGHBOURS {
+            let next = (cell.0 + dx, cell.1 + dz);
+            let Some(&nh) = elev_map.get(&next) else { continue };
+            let dist = if dx != 0 && dz != 0 { std::f64::consts::SQRT_2 } else { 1.0 };
+            let dh   = nh as f64 - h;
+            let step = match carve.kind {
+                CarveKind::River => dist * (1.0 + 50.0 * dh.max(0.0)),
+                CarveKind::Path  => dist * (1.0 + 4.0 * dh.abs()),
+            };
+            let next_cost = cost + (step * 1000.0) as u64;
+            if best.get(&next).is_none_or(|&c| next_cost < c) {
+                best.insert(next, next_cost);
+                prev.insert(next, cell);
+                heap.push(Reverse((next_cost, next)));
+            }
+        }
+    }
+
+    Vec::new()
+}
+
+// ── Channel carving ────────────────────────────────────────────────────────
+
+fn carve_channel(
+    grid:     &mut VoxelGrid,
+    cells:    &[(i32, i32)],
+    elev_map: &HashMap<(i32, i32), i32>,
+    carve:    &ResolvedCarve,
+    atom_id:  u16,
+) {
+    // Every column within width/2 of the centre line takes the surface level
+    // of the lowest route cell covering it, so the channel stays level across.
+    let half = carve.width / 2.0;
+    let r    = half.ceil() as i32;
+    let mut level: HashMap<(i32, i32), i32> = HashMap::new();
+
+    for &(cx, cz) in cells {
+        let centre = elev_map[&(cx, cz)];
+        for dz in -r..=r {
+            for dx in -r..=r {
+                if ((dx*dx + dz*dz) as f64).sqrt() > half { continue; }
+                let col = (cx + dx, cz + dz);
+                if !elev_map.contains_key(&col) { continue; }
+                let entry = level.entry(col).or_insert(centre);
+                *entry = (*entry).min(centre);
+            }
+        }
+    }
+
+    for ((x, z), surface) in level {
+        // Rivers sit one voxel below their banks; paths are flush.
+        let top = match carve.kind {
+            CarveKind::River => surface - 1,
+            CarveKind::Path  => surface,
+        }.max(0);
+
+        for y in (top + 1)..grid.height as i32 {
+            grid.set(x, y, z, 0);
+        }
+        for y in (top - carve.depth + 1).max(0)..=top {
+            grid.set(x, y, z, atom_id);
+        }
+    }
+}
+
+/// `river` or `path`, for messages.
+pub fn kind_label(kind: CarveKind) -> &'static str {
+    match kind {
+        CarveKind::River => "river",
+        CarveKind::Path  => "path",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn carve(kind: CarveKind, from: (i32, i32), to: CarveTarget) -> ResolvedCarve {
+        ResolvedCarve {
+            name: "Route".into(), kind, from, to,
+            width: 1.0, depth: 1, sea_level: 0, material_index: 0,
+        }
+    }
+
+    /// Terrain whose column (x, z) is solid up to `height(x, z)`.
+    fn terrain(w: u32, d: u32, height: impl Fn(i32, i32) -> i32) -> VoxelGrid {
+        let mut grid = VoxelGrid::new(w, 12, d);
+        for z in 0..d as i32 {
+            for x in 0..w as i32 {
+                for y in 0..=height(x, z) { grid.set(x, y, z, 1); }
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn river_runs_downhill_to_the_sea() {
+        // Falls towards x = 0; the long z edges are far enough away that
+        // going downhill is cheaper, and the near x edge is uphill.
+        let grid = terrain(21, 61, |x, _| x / 2);
+        let elev = build_elevation_map(&grid);
+        let cells = trace_route(&carve(CarveKind::River, (18, 30), CarveTarget::Sea), (18, 30), (0, 0), &elev);
+
+        let heights: Vec<i32> = cells.iter().map(|c| elev[c]).collect();
+        assert_eq!(cells[0], (18, 30));
+        assert_eq!(*heights.last().unwrap(), 0);
+        assert!(heights.windows(2).all(|h| h[1] <= h[0]), "{heights:?}");
+    }
+
+    #[test]
+    fn path_goes_round_a_wall_through_its_gap() {
+        let grid = terrain(21, 21, |x, z| if x == 10 && z < 17 { 8 } else { 1 });
+        let elev = build_elevation_map(&grid);
+        let cells = trace_route(&carve(CarveKind::Path, (2, 2), CarveTarget::Point(18, 2)), (2, 2), (0, 0), &elev);
+
+        assert_eq!((cells[0], *cells.last().unwrap()), ((2, 2), (18, 2)));
+        assert!(cells.iter().all(|&(x, z)| x != 10 || z >= 17), "{cells:?}");
+    }
+
+    #[test]
+    fn river_channel_sits_below_its_banks() {
+        let mut grid = terrain(5, 5, |_, _| 3);
+        let elev = build_elevation_map(&grid);
+        let river = carve(CarveKind::River, (0, 2), CarveTarget::Sea);
+        carve_channel(&mut grid, &[(0, 2), (1, 2), (2, 2)], &elev, &river, 7);
+
+        assert_eq!((grid.get(1, 3, 2), grid.get(1, 2, 2), grid.get(1, 1, 2)), (0, 7, 1));
+        assert_eq!(grid.get(1, 3, 1), 1);    // the bank is untouched
+        assert_eq!(grid.get(3, 3, 2), 1);    // and so is the ground past the end
+    }
+
+    #[test]
+    fn distance_to_matches_a_full_scan() {
+        // A long diagonal plus a far outlier, so some queries need a wide search
+        let mut cells: Vec<(i32, i32)> = (0..200).map(|i| (i, i / 3)).collect();
+        cells.push((-150, 400));
+        let route = CarvedRoute::new("Route".into(), CarveKind::Path, cells.clone());
+
+        for (x, z) in [(0, 0), (50, 60), (-100, 380), (300, -40), (-500, -500), (90, 30)] {
+            let scan = cells.iter()
+                .map(|&(cx, cz)| (((x - cx).pow(2) + (z - cz).pow(2)) as f64).sqrt())
+                .fold(f64::INFINITY, f64::min);
+            assert_eq!(route.distance_to(x, z), scan, "at ({x}, {z})");
+        }
+        assert_eq!(CarvedRoute::new("Empty".into(), CarveKind::Path, Vec::new()).distance_to(0, 0), f64::INFINITY);
+    }
+}
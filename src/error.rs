@@ -41,6 +41,16 @@ pub enum MoxiError {
     UndefinedMaterial { name: String, span: Span },
     /// An atom referenced in a material isn't declared.
     UndefinedAtom { name: String, span: Span },
+    /// A required `key = value` property was not given.
+    MissingProperty { key: String, owner: String, span: Span },
+    /// A property was given a value of the wrong form.
+    InvalidValue { key: String, expected: String, span: Span },
+    /// Declarations depend on each other in a loop: `cycle` starts and ends
+    /// with the same name.
+    DependencyCycle { cycle: Vec<String>, span: Span },
+    /// Two relations pull the same part along the same axis.  `first` is
+    /// the earlier of the two.
+    OverConstrained { name: String, axis: char, first: Span, span: Span },
 
     // ── Constraint validator ───────────────────────────────────────────────
     /// A declared constraint was violated after geometry resolution.
@@ -66,6 +76,14 @@ impl std::fmt::Display for MoxiError {
                 write!(f, "[{span}] material '{name}' is not defined"),
             MoxiError::UndefinedAtom { name, span } =>
                 write!(f, "[{span}] atom '{name}' is not defined"),
+            MoxiError::MissingProperty { key, owner, span } =>
+                write!(f, "[{span}] '{owner}' is missing required property '{key}'"),
+            MoxiError::InvalidValue { key, expected, span } =>
+                write!(f, "[{span}] invalid value for '{key}', expected {expected}"),
+            MoxiError::DependencyCycle { cycle, span } =>
+                write!(f, "[{span}] dependency cycle: {}", cycle.join(" → ")),
+            MoxiError::OverConstrained { name, axis, first, span } =>
+                write!(f, "[{span}] '{name}' is over-constrained: its {axis} is already set by the relation at {first}"),
             MoxiError::ConstraintViolation { description } =>
                 write!(f, "constraint violated: {description}"),
         }
@@ -88,8 +88,11 @@ fn write_mtl(color_to_mat: &HashMap<String, String>, path: &str) -> anyhow::Resu
     Ok(())
 }
 
+/// 8 corner vertices + 6 quad faces (vertex indices) of one cube.
+type CubeGeometry = (Vec<(f32, f32, f32)>, Vec<[usize; 4]>);
+
 /// Returns the 8 corner vertices and 6 quad faces for a unit cube at voxel position.
-fn cube_geometry(v: &Voxel) -> (Vec<(f32, f32, f32)>, Vec<[usize; 4]>) {
+fn cube_geometry(v: &Voxel) -> CubeGeometry {
     let (x, y, z) = (v.x as f32, v.y as f32, v.z as f32);
 
     let verts = vec![
@@ -1,14 +1,37 @@
 // src/generator.rs
 //
 // The generator pass runs after geometry compilation and relation resolution.
-// It reads `GeneratorDecl` blocks and scatters instances of a target entity
-// over a terrain surface, respecting `where`, `avoid`, and `min_spacing`.
+// It reads `GeneratorDecl` blocks and scatters instances of a target entity —
+// or a weighted pool of them — over one or more surfaces (`over = …`, default:
+// the primary terrain), respecting `where`, `avoid`, `min_spacing` and the
+// box each instance fills (instances never intersect unless `overlap = allow`).
+//
+// A generator with `inside = Host` scatters through the host's filled volume
+// instead: entity instances are embedded centred on host voxels, and
+// `mode = vein(…)` grows random-walk veins of an atom through the host.
+//
+// `surface = side|bottom|any` considers every exposed voxel face of the
+// surfaces instead of only the top of each column, and grows instances out
+// along the face normal (`align = normal`, the default there) — vines on
+// cliffs, stalactites on ceilings.
+//
+// Every instance may also be turned (`rotate`), resized (`scale`) and have its
+// shape parameters jittered (`jitter`).  All choices derive from the
+// generator's `seed` and the instance position, so output is reproducible.
 //
 // Output: a list of placed instances with world-space (x, y, z) positions.
 // The main pipeline merges these into the scene VoxelGrid.
 
-use std::collections::HashMap;
-use crate::ast::{GeneratorDecl, Expr, BinOp};
+use std::cell::OnceCell;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use crate::ast::{CarveKind, GeneratorDecl, Expr, BinOp, ShapeExpr};
+use crate::analysis::settle_entity;
+use crate::carve::CarvedRoute;
+use crate::geom::{tilt_to_normal, Axis};
+use crate::geometry::{compile_entity, terrain_noise, CompiledEntity};
+use crate::relation_resolver::{assemble_entity, BBox};
+use crate::resolver::{around_names, ResolvedEntity, ResolvedScene};
 use crate::voxel::VoxelGrid;
 
 // ── Public types ───────────────────────────────────────────────────────────
@@ -18,101 +41,1255 @@ use crate::voxel::VoxelGrid;
 pub struct PlacedInstance {
     pub generator_name: String,
     pub target_name:    String,
+    /// Index of `target_name` in the generator's scatter pool.
+    pub variant:        usize,
+    pub x: i32,
+    pub y: i32,   // the surface voxel the instance grows from, or its host voxel
+    pub z: i32,
+    /// Outward normal of the face the instance grows from: (0, 1, 0) stands
+    /// on top of (x, y, z); (0, 0, 0) is embedded, centred on it.
+    pub normal:         (i32, i32, i32),
+    /// Where the instance's own +y points after tilting — `normal`
+    /// with `align = normal`, (0, 1, 0) with `align = up`.
+    pub up:             (i32, i32, i32),
+    /// Quarter turns about the vertical axis.
+    pub rotation:       i32,
+    pub scale:          f64,
+    /// Fraction by which shape parameters were jittered (0 = none).
+    pub jitter:         f64,
+    /// Per-instance seed driving the jitter.
+    pub seed:           u64,
+}
+
+/// One voxel written into a host entity by a `vein` generator.
+#[derive(Debug, Clone)]
+pub struct PlacedVoxel {
+    pub generator_name: String,
+    /// The entity the voxel replaces a voxel of.
+    pub host:           String,
     pub x: i32,
-    pub y: i32,   // surface elevation at this column
+    pub y: i32,
     pub z: i32,
+    pub atom_id:        u16,
+}
+
+/// The full output of running all generators over the world.
+#[derive(Debug, Clone, Default)]
+pub struct GeneratorOutput {
+    pub instances: Vec<PlacedInstance>,
+    pub voxels:    Vec<PlacedVoxel>,
+    /// One per generator, in the order they ran.
+    pub reports:   Vec<GeneratorReport>,
+}
+
+/// Where one generator's candidate spots went.  Every candidate is counted
+/// at most once, under the first check that turned it down; candidates left
+/// over once the limit is reached are never tried.
+#[derive(Debug, Clone, Default)]
+pub struct GeneratorReport {
+    pub name:             String,
+    /// Columns, host voxels or exposed faces considered (inside the `around`
+    /// ring, for child generators).
+    pub candidates:       usize,
+    pub failed_where:     usize,
+    /// On an avoided surface, or within `avoid_radius` of an avoided instance.
+    pub rejected_avoid:   usize,
+    /// Footprint would intersect an earlier instance.
+    pub rejected_overlap: usize,
+    /// Closer than `min_spacing` (or the Poisson radius) to a sibling.
+    pub rejected_spacing: usize,
+    /// Instances placed, or veins started.
+    pub placed:           usize,
+    /// The generator's `count`, if it has one.
+    pub requested:        Option<usize>,
+    /// Names in `over` (or `inside`) with no surface in this world:
+    /// `terrain` without a heightfield, or an entity that is only drawn as
+    /// generator instances.
+    pub missing:          Vec<String>,
+}
+
+impl GeneratorReport {
+    fn new(gen: &GeneratorDecl) -> Self {
+        Self {
+            name:      gen.name.name.clone(),
+            requested: gen.props.iter().any(|p| p.key == "count").then(|| prop_i64(gen, "count", 50) as usize),
+            ..Self::default()
+        }
+    }
+
+    /// True when fewer instances were placed than `count` asked for.
+    pub fn fell_short(&self) -> bool {
+        self.requested.is_some_and(|n| self.placed < n)
+    }
+}
+
+impl std::fmt::Display for GeneratorReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "placed {}", self.placed)?;
+        if let Some(n) = self.requested {
+            write!(f, " of {n}")?;
+        }
+        write!(f, " from {} candidates", self.candidates)?;
+        let rejected = [
+            (self.failed_where,     "failed `where`"),
+            (self.rejected_avoid,   "avoided"),
+            (self.rejected_overlap, "overlapping"),
+            (self.rejected_spacing, "too close"),
+        ];
+        let mut first = true;
+        for (n, why) in rejected.iter().filter(|(n, _)| *n > 0) {
+            write!(f, "{}{n} {why}", if first { " (" } else { ", " })?;
+            first = false;
+        }
+        if !first {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// A compiled grid placed in the world that generators can scatter over.
+#[derive(Debug, Clone, Copy)]
+pub struct Surface<'a> {
+    pub name:   &'a str,
+    pub grid:   &'a VoxelGrid,
+    /// World position of grid voxel (0, 0, 0).
+    pub offset: (i32, i32, i32),
 }
 
-/// The full output of running all generators over a terrain grid.
-pub type GeneratorOutput = Vec<PlacedInstance>;
+/// Axis-aligned extent of a scatter target's assembled grid, in voxels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Footprint {
+    pub width:  i32,
+    pub height: i32,
+    pub depth:  i32,
+}
+
+impl Footprint {
+    /// The voxels an instance of this size fills when it grows from `pos`
+    /// out of the face with outward `normal`, its own +y turned to `up`.
+    /// Matches how the world builder offsets instance grids.
+    fn rect_at(&self, pos: (i32, i32, i32), normal: (i32, i32, i32), up: (i32, i32, i32)) -> Rect {
+        let (w, h, d) = (self.width, self.height, self.depth);
+        let dims = match up {
+            (_, 0, 0) => (h, w, d),
+            (0, 0, _) => (w, d, h),
+            _         => (w, h, d),
+        };
+        let (x0, y0, z0) = origin_at(pos, normal, (dims.0 as u32, dims.1 as u32, dims.2 as u32));
+        Rect { x0, y0, z0, x1: x0 + dims.0 - 1, y1: y0 + dims.1 - 1, z1: z0 + dims.2 - 1 }
+    }
+}
 
 // ── Entry point ────────────────────────────────────────────────────────────
 
-/// Run all generators against the compiled terrain grid.
+/// Run all generators over the world's surfaces.
+///
+/// `surfaces` — every rendered entity grid with its world offset
+/// `terrain`  — name of the primary terrain, used when a generator has no `over`
+/// `scene`    — resolved scene: generator declarations, atoms, materials
+/// `compiled` — compiled entities, for the scatter targets' footprints
+/// `routes`   — carved rivers and paths, for `distance_to(<name>)`
+///
+/// `over` names one surface, a list of surfaces, `terrain` (the default) or
+/// `world` (every surface).  Where surfaces stack, the highest voxel wins.
 ///
-/// `terrain_grid` — the voxel grid of the terrain (from heightfield stamp)
-/// `generators`   — parsed generator declarations
+/// Generators run in declaration order, except that a child generator
+/// (`around = Parent`) always runs after its parents.  `avoid` and footprint
+/// overlap see every instance placed by the generators that ran before it;
+/// `avoid` also sees the avoided entities' own layers in `surfaces`.
 ///
-/// Returns all placed instances across all generators.
+/// Returns all placed instances and vein voxels across all generators, in
+/// world coordinates.
 pub fn run_generators(
-    terrain_grid: &VoxelGrid,
-    generators:   &[GeneratorDecl],
+    surfaces: &[Surface],
+    terrain:  Option<&str>,
+    scene:    &ResolvedScene,
+    compiled: &[CompiledEntity],
+    routes:   &[CarvedRoute],
 ) -> GeneratorOutput {
-    let mut all = Vec::new();
+    let mut all    = Vec::new();
+    let mut voxels = Vec::new();
+
+    // Surface maps are shared by every generator scattering over the same set
+    let mut surface_maps: HashMap<Vec<&str>, SurfaceMap> = HashMap::new();
+
+    let footprints = target_footprints(scene, compiled);
+
+    // Atom and material names, for `surface_material == Sand`
+    let mut atom_ids: HashMap<String, u16> = HashMap::new();
+    for (i, atom) in scene.atoms.iter().enumerate() {
+        atom_ids.insert(atom.name.clone(), i as u16 + 1);
+    }
+    for mat in &scene.materials {
+        atom_ids.insert(mat.name.clone(), mat.atom_index as u16 + 1);
+    }
+
+    // One hash over every placed footprint, sized to the largest target
+    let cell = footprints.values()
+        .map(|f| f.width.max(f.depth))
+        .max()
+        .unwrap_or(1)
+        .max(4);
+    let mut placed_hash = SpatialHash::new(cell);
+    let mut reports = Vec::new();
+
+    for gen in run_order(&scene.generators).into_iter().map(|i| &scene.generators[i]) {
+        let (over, missing) = surface_names(gen, surfaces, terrain);
+        if over.is_empty() {
+            reports.push(GeneratorReport { missing, ..GeneratorReport::new(gen) });
+            continue;
+        }
+        let chosen: Vec<Surface> = surfaces.iter()
+            .filter(|s| over.contains(&s.name))
+            .copied()
+            .collect();
+        let surface = surface_maps.entry(over).or_insert_with(|| SurfaceMap::build(&chosen));
+
+        let avoid = AvoidSpec::from_generator(gen, scene, surfaces);
+        let pool: Vec<Footprint> = gen.scatter_targets.iter()
+            .map(|t| footprints.get(&t.name).copied().unwrap_or(Footprint { width: 1, height: 1, depth: 1 }))
+            .collect();
+        let host = volume_host(gen).and_then(|h| surfaces.iter().find(|s| s.name == h));
+        let ctx = GenCtx {
+            surface, host, layers: &chosen, routes,
+            avoid: &avoid, footprints: &pool, atom_ids: &atom_ids,
+        };
+        let faces = face_normals(gen);
+        let report = if host.is_some() {
+            run_volume_generator(gen, &ctx, &mut all, &mut placed_hash, &mut voxels)
+        } else if faces.is_empty() {
+            run_one_generator(gen, &ctx, &mut all, &mut placed_hash)
+        } else {
+            run_face_generator(gen, &ctx, &faces, &mut all, &mut placed_hash)
+        };
+        reports.push(GeneratorReport { missing, ..report });
+    }
+
+    GeneratorOutput { instances: all, voxels, reports }
+}
+
+/// The face normals `surface = side|bottom|any` scatters on; empty for the
+/// default `top`, which keeps to the highest voxel of each column.
+fn face_normals(gen: &GeneratorDecl) -> Vec<(i32, i32, i32)> {
+    let kind = gen.props.iter().find(|p| p.key == "surface").map(|p| prop_str_val(&p.value));
+    match kind.as_deref() {
+        Some("side")   => vec![(1, 0, 0), (-1, 0, 0), (0, 0, 1), (0, 0, -1)],
+        Some("bottom") => vec![(0, -1, 0)],
+        Some("any")    => FACES.to_vec(),
+        _              => Vec::new(),
+    }
+}
 
-    // Build elevation map: (x, z) → highest filled y
-    let elev_map = build_elevation_map(terrain_grid);
+/// `align = normal` tilts instances to their face; `align = up` keeps them
+/// upright.  Faces default to `normal`, column tops to `up`.
+fn align_to_normal(gen: &GeneratorDecl, default: bool) -> bool {
+    match gen.props.iter().find(|p| p.key == "align").map(|p| prop_str_val(&p.value)) {
+        Some(a) => a == "normal",
+        None    => default,
+    }
+}
+
+/// The entity named by `inside = …`.
+fn volume_host(gen: &GeneratorDecl) -> Option<&str> {
+    match gen.props.iter().find(|p| p.key == "inside").map(|p| &p.value) {
+        Some(Expr::Ident(i)) => Some(i.name.as_str()),
+        _                    => None,
+    }
+}
+
+/// Declaration order, with every generator moved after the generators it is
+/// `around`.  The resolver rejects cycles; any left over run last.
+fn run_order(generators: &[GeneratorDecl]) -> Vec<usize> {
+    let parents: Vec<Vec<usize>> = generators.iter()
+        .map(|g| around_names(g).iter()
+            .filter_map(|n| generators.iter().position(|p| &p.name.name == n))
+            .collect())
+        .collect();
+
+    let mut order: Vec<usize> = Vec::new();
+    let mut done = vec![false; generators.len()];
+    // Repeatedly take the first generator whose parents have all run
+    while let Some(next) = (0..generators.len())
+        .find(|&i| !done[i] && parents[i].iter().all(|&p| done[p]))
+    {
+        done[next] = true;
+        order.push(next);
+    }
+    order.extend((0..generators.len()).filter(|&i| !done[i]));
+    order
+}
+
+/// The surfaces a generator scatters over, and the names it asked for that
+/// have no surface in this world.
+fn surface_names<'a>(
+    gen:      &GeneratorDecl,
+    surfaces: &[Surface<'a>],
+    terrain:  Option<&'a str>,
+) -> (Vec<&'a str>, Vec<String>) {
+    // A volume generator samples its host's own top surface
+    let over = volume_host(gen).map(|_| "inside").unwrap_or("over");
+    let requested: Vec<String> = match gen.props.iter().find(|p| p.key == over) {
+        Some(p) => match &p.value {
+            Expr::List(items) => items.iter().map(prop_str_val).collect(),
+            other             => vec![prop_str_val(other)],
+        },
+        None => vec!["terrain".to_string()],
+    };
+
+    let mut names: Vec<&str> = Vec::new();
+    let mut missing = Vec::new();
+    for name in requested {
+        match name.as_str() {
+            "terrain" => match terrain {
+                Some(t) => names.push(t),
+                None    => missing.push(name),
+            },
+            "world" => names.extend(surfaces.iter().map(|s| s.name)),
+            other => match surfaces.iter().find(|s| s.name == other) {
+                Some(s) => names.push(s.name),
+                None    => missing.push(name),
+            },
+        }
+    }
+
+    names.sort_unstable();
+    names.dedup();
+    (names, missing)
+}
 
-    for gen in generators {
-        let instances = run_one_generator(gen, &elev_map, terrain_grid);
-        all.extend(instances);
+/// Assemble each scatter target once and measure its extent.
+fn target_footprints(
+    scene:    &ResolvedScene,
+    compiled: &[CompiledEntity],
+) -> HashMap<String, Footprint> {
+    let mut out = HashMap::new();
+    for name in scene.generators.iter().flat_map(|g| &g.scatter_targets).map(|t| &t.name) {
+        if out.contains_key(name) { continue; }
+        let found = compiled.iter()
+            .zip(scene.entities.iter())
+            .find(|(e, _)| &e.name == name);
+        if let Some((ent, resolved)) = found {
+            let (w, h, d) = assemble_entity(&ent.parts, &resolved.relations, scene.voxel_size).dims();
+            out.insert(name.clone(), Footprint { width: w as i32, height: h as i32, depth: d as i32 });
+        }
+    }
+    out
+}
+
+/// Assemble the voxel grid of one placed instance: jittered parameters,
+/// then rotation, then scale.  `None` if the target entity does not exist.
+pub fn instance_grid(
+    inst:     &PlacedInstance,
+    scene:    &ResolvedScene,
+    compiled: &[CompiledEntity],
+) -> Option<VoxelGrid> {
+    let (ent, resolved) = compiled.iter()
+        .zip(scene.entities.iter())
+        .find(|(e, _)| e.name == inst.target_name)?;
+
+    let mut grid = if inst.jitter > 0.0 {
+        let jittered = jitter_entity(resolved, inst.jitter, inst.seed);
+        let recompiled = compile_entity(&jittered, scene, ent.voxel_size);
+        let grid = assemble_entity(&recompiled.parts, &jittered.relations, scene.voxel_size);
+        settle_entity(grid, &recompiled.parts, &jittered, scene.voxel_size)
+    } else {
+        let grid = assemble_entity(&ent.parts, &resolved.relations, scene.voxel_size);
+        settle_entity(grid, &ent.parts, resolved, scene.voxel_size)
+    };
+    if inst.rotation.rem_euclid(4) != 0 {
+        grid = grid.rotated(Axis::Y, inst.rotation);
+    }
+    if inst.scale != 1.0 {
+        grid = grid.scaled(inst.scale);
     }
+    if let Some((axis, turns)) = tilt_to_normal(inst.up) {
+        grid = grid.rotated(axis, turns);
+    }
+    Some(grid)
+}
+
+/// World position of voxel (0, 0, 0) of an instance grid with dimensions
+/// `dims`: flush against the face it grows from along the normal, centred
+/// across it.
+pub fn instance_origin(inst: &PlacedInstance, dims: (u32, u32, u32)) -> (i32, i32, i32) {
+    origin_at((inst.x, inst.y, inst.z), inst.normal, dims)
+}
 
-    all
+/// `instance_origin` for a spot that has no instance yet.
+fn origin_at(pos: (i32, i32, i32), normal: (i32, i32, i32), dims: (u32, u32, u32)) -> (i32, i32, i32) {
+    let place = |p: i32, n: i32, dim: u32| match n {
+        1  => p + 1,
+        -1 => p - dim as i32,
+        _  => p - dim as i32 / 2,
+    };
+    let (nx, ny, nz) = normal;
+    (place(pos.0, nx, dims.0), place(pos.1, ny, dims.1), place(pos.2, nz, dims.2))
 }
 
 // ── Per-generator execution ────────────────────────────────────────────────
 
+/// Everything one generator run reads besides its own declaration.
+struct GenCtx<'a> {
+    surface:    &'a SurfaceMap,
+    /// The volume scattered through, for `inside = …` generators.
+    host:       Option<&'a Surface<'a>>,
+    /// The grids named by `over`, for face scatter.
+    layers:     &'a [Surface<'a>],
+    routes:     &'a [CarvedRoute],
+    avoid:      &'a AvoidSpec,
+    /// Unrotated, unscaled footprint of each scatter variant.
+    footprints: &'a [Footprint],
+    atom_ids:   &'a HashMap<String, u16>,
+}
+
+/// Place one generator's instances, appending to `all` and `placed_hash`.
 fn run_one_generator(
-    gen:      &GeneratorDecl,
-    elev_map: &HashMap<(i32,i32), i32>,
-    _grid:     &VoxelGrid,
-) -> Vec<PlacedInstance> {
+    gen:         &GeneratorDecl,
+    ctx:         &GenCtx,
+    all:         &mut Vec<PlacedInstance>,
+    placed_hash: &mut SpatialHash,
+) -> GeneratorReport {
     // Extract generator properties
-    let count       = prop_i64(gen, "count",       50)  as usize;
-    let min_spacing = prop_f64(gen, "min_spacing",  3.0);
-    let seed        = prop_i64(gen, "seed",         42)  as u64;
+    let seed         = prop_i64(gen, "seed",         42)  as u64;
+    let sea_level    = prop_i64(gen, "sea_level",    0)   as i32;
+    let mode         = Mode::from_generator(gen);
 
     // `where` condition AST node (if any)
     let condition = gen.props.iter().find(|p| p.key == "where").map(|p| &p.value);
 
-    // `avoid` name (if any) — we skip cells where avoid-named atom is present
-    let _avoid = gen.props.iter().find(|p| p.key == "avoid").map(|p| prop_str_val(&p.value));
+    let fields = DistanceFields::new(ctx.surface, ctx.routes, sea_level);
+
+    // Child generators only consider columns in a ring around parent instances
+    let around = around_columns(gen, all);
 
     // Candidate cells: all (x,z) positions in the elevation map
-    let mut candidates: Vec<(i32, i32, i32)> = elev_map
-        .iter()
-        .filter_map(|(&(x, z), &y)| {
-            let ctx = EvalCtx { x, y, z, elev_map };
-            if let Some(cond) = condition {
-                if !eval_bool(cond, &ctx) { return None; }
-            }
-            Some((x, y, z))
-        })
-        .collect();
+    let mut report = GeneratorReport::new(gen);
+    let mut candidates: Vec<(i32, i32, i32)> = Vec::new();
+    for (&(x, z), &y) in &ctx.surface.elev {
+        if around.as_ref().is_some_and(|cols| !cols.contains(&(x, z))) { continue; }
+        report.candidates += 1;
+        // Never grow out of an avoided surface (water, rock, …)
+        if ctx.avoid.atoms.contains(&ctx.surface.atoms[&(x, z)]) {
+            report.rejected_avoid += 1;
+            continue;
+        }
+        let eval = EvalCtx {
+            x, y, z, seed, sea_level,
+            surface:  ctx.surface,
+            host:     None,
+            routes:   ctx.routes,
+            fields:   &fields,
+            atom_ids: ctx.atom_ids,
+        };
+        if condition.is_some_and(|cond| !eval_bool(cond, &eval)) {
+            report.failed_where += 1;
+            continue;
+        }
+        candidates.push((x, y, z));
+    }
 
-    // Shuffle candidates deterministically using our hash
+    // HashMap order differs between runs — sort first so the seeded
+    // shuffle is the only source of variation.
+    candidates.sort_unstable();
     shuffle(&mut candidates, seed);
 
-    // Place up to `count` instances with minimum spacing enforced
-    let mut placed: Vec<PlacedInstance> = Vec::new();
+    let area  = candidates.len() as f64;
+    let limit = instance_limit(gen, candidates.len());
+
+    let mut placer = Placer::new(gen, ctx, seed, report);
+    match mode {
+        // Veins only grow inside a volume; the resolver rejects them here
+        Mode::Uniform | Mode::Vein { .. } => {
+            for &(x, y, z) in &candidates {
+                if placer.report.placed >= limit { break; }
+                placer.try_place(((x, y, z), UP), all, placed_hash);
+            }
+        }
+        Mode::Poisson { radius } => {
+            // Dart throwing: random order, reject anything within `radius`
+            let radius = radius.unwrap_or_else(|| (area / limit.max(1) as f64).sqrt() * 0.75);
+            placer.min_spacing = placer.min_spacing.max(radius);
+            for &(x, y, z) in &candidates {
+                if placer.report.placed >= limit { break; }
+                placer.try_place(((x, y, z), UP), all, placed_hash);
+            }
+        }
+        Mode::Cluster { radius, per_cluster } => {
+            let index: HashMap<(i32,i32), i32> = candidates.iter().map(|&(x, y, z)| ((x, z), y)).collect();
+            let reach = radius.ceil() as i32;
+            let mut centres: Vec<(i32, i32)> = Vec::new();
+
+            for &(cx, _, cz) in &candidates {
+                if placer.report.placed >= limit { break; }
+                let near = |&(px, pz): &(i32, i32)| {
+                    let (dx, dz) = ((cx - px) as f64, (cz - pz) as f64);
+                    (dx*dx + dz*dz).sqrt() < radius
+                };
+                if centres.iter().any(near) { continue; }
+                centres.push((cx, cz));
 
-    'outer: for (x, y, z) in candidates {
-        if placed.len() >= count { break; }
+                let mut members: Vec<(i32, i32, i32)> = Vec::new();
+                for z in cz - reach..=cz + reach {
+                    for x in cx - reach..=cx + reach {
+                        if !near(&(x, z)) { continue; }
+                        if let Some(&y) = index.get(&(x, z)) { members.push((x, y, z)); }
+                    }
+                }
+                shuffle(&mut members, seed ^ hash(centres.len() as u64));
 
-        // Check min_spacing against all already-placed instances
-        for p in &placed {
-            let dx = (x - p.x) as f64;
-            let dz = (z - p.z) as f64;
-            if (dx*dx + dz*dz).sqrt() < min_spacing {
-                continue 'outer;
+                let mut in_cluster = 0;
+                for (x, y, z) in members {
+                    if in_cluster >= per_cluster || placer.report.placed >= limit { break; }
+                    if placer.try_place(((x, y, z), UP), all, placed_hash) { in_cluster += 1; }
+                }
+            }
+        }
+        Mode::Grid { spacing, jitter, hex } => {
+            let index: HashMap<(i32,i32), i32> = candidates.iter().map(|&(x, y, z)| ((x, z), y)).collect();
+            let mut points = lattice(&candidates, spacing, jitter, hex, seed);
+            points.retain(|p| index.contains_key(p));
+            // Shuffled so a `count` below the lattice size thins it evenly
+            shuffle(&mut points, seed);
+            for (x, z) in points {
+                if placer.report.placed >= limit { break; }
+                placer.try_place(((x, index[&(x, z)], z), UP), all, placed_hash);
             }
         }
+    }
+    placer.report
+}
+
+/// For `around = Parent, radius = lo..hi`: every column whose horizontal
+/// distance to a parent instance lies within [lo, hi].  `radius = r` means
+/// `0..r`; the default is `1..4`.
+fn around_columns(gen: &GeneratorDecl, all: &[PlacedInstance]) -> Option<HashSet<(i32, i32)>> {
+    let parents = around_names(gen);
+    if parents.is_empty() { return None; }
+
+    let (lo, hi) = match gen.props.iter().find(|p| p.key == "radius").map(|p| &p.value) {
+        Some(Expr::Range { lo, hi }) => (expr_f64(lo), expr_f64(hi)),
+        Some(other)                  => (0.0, expr_f64(other)),
+        None                         => (1.0, 4.0),
+    };
+    let reach = hi.ceil() as i32;
+
+    let mut cols = HashSet::new();
+    for p in all.iter().filter(|p| parents.contains(&p.generator_name)) {
+        for dz in -reach..=reach {
+            for dx in -reach..=reach {
+                let d = ((dx*dx + dz*dz) as f64).sqrt();
+                if d >= lo && d <= hi { cols.insert((p.x + dx, p.z + dz)); }
+            }
+        }
+    }
+    Some(cols)
+}
+
+// ── Volume scatter ─────────────────────────────────────────────────────────
+//
+// `inside = Host` generators take every filled host voxel as a candidate
+// (under the `around` ring, for child generators).  Entity instances are
+// embedded centred on their voxel, kept `min_spacing` apart in 3D and go
+// through the same overlap and `avoid` checks as any other instance.
+// `mode = vein(length, branch)` instead starts `count` random walks that
+// turn host voxels into the target atom, forking with probability `branch`
+// at each step.
+
+fn run_volume_generator(
+    gen:         &GeneratorDecl,
+    ctx:         &GenCtx,
+    all:         &mut Vec<PlacedInstance>,
+    placed_hash: &mut SpatialHash,
+    voxels:      &mut Vec<PlacedVoxel>,
+) -> GeneratorReport {
+    let mut report = GeneratorReport::new(gen);
+    let Some(host) = ctx.host else { return report };
+    let seed      = prop_i64(gen, "seed",      42) as u64;
+    let sea_level = prop_i64(gen, "sea_level", 0)  as i32;
+    let condition = gen.props.iter().find(|p| p.key == "where").map(|p| &p.value);
+    let fields    = DistanceFields::new(ctx.surface, ctx.routes, sea_level);
+    let around    = around_columns(gen, all);
+
+    let (ox, oy, oz) = host.offset;
+    let mut candidates: Vec<(i32, i32, i32)> = Vec::new();
+    for (x, y, z, atom) in host.grid.iter_filled() {
+        if around.as_ref().is_some_and(|cols| !cols.contains(&(x as i32 + ox, z as i32 + oz))) { continue; }
+        report.candidates += 1;
+        if ctx.avoid.atoms.contains(&atom) {
+            report.rejected_avoid += 1;
+            continue;
+        }
+        let (x, y, z) = (x as i32 + ox, y as i32 + oy, z as i32 + oz);
+        let eval = EvalCtx {
+            x, y, z, seed, sea_level,
+            surface:  ctx.surface,
+            host:     Some(host),
+            routes:   ctx.routes,
+            fields:   &fields,
+            atom_ids: ctx.atom_ids,
+        };
+        if condition.is_some_and(|cond| !eval_bool(cond, &eval)) {
+            report.failed_where += 1;
+            continue;
+        }
+        candidates.push((x, y, z));
+    }
+    shuffle(&mut candidates, seed);
+    let limit = instance_limit(gen, candidates.len());
+
+    match Mode::from_generator(gen) {
+        Mode::Vein { length, branch } => {
+            let Some(&atom_id) = gen.scatter_targets.first().and_then(|t| ctx.atom_ids.get(&t.name)) else { return report };
+            let cells: HashSet<(i32, i32, i32)> = candidates.iter().copied().collect();
+            let mut grown: HashSet<(i32, i32, i32)> = HashSet::new();
+
+            for (v, &start) in candidates.iter().take(limit).enumerate() {
+                report.placed += 1;
+                let vein_seed = hash(seed ^ hash(v as u64 + 1));
+                let mut step = 0u64;
+                let mut walkers = vec![(start, length)];
+                while let Some((mut pos, mut left)) = walkers.pop() {
+                    while left > 0 {
+                        if grown.insert(pos) {
+                            voxels.push(PlacedVoxel {
+                                generator_name: gen.name.name.clone(),
+                                host:           host.name.to_string(),
+                                x: pos.0, y: pos.1, z: pos.2,
+                                atom_id,
+                            });
+                        }
+                        left -= 1;
+                        step += 1;
+
+                        // Fork: the branch carries on with half of what is left
+                        if roll(vein_seed, step as i32, 0, 1) < branch {
+                            walkers.push((pos, left / 2));
+                        }
+
+                        // Step to a random face neighbour that is still host
+                        let open: Vec<(i32, i32, i32)> = FACES.iter()
+                            .map(|&(dx, dy, dz)| (pos.0 + dx, pos.1 + dy, pos.2 + dz))
+                            .filter(|p| cells.contains(p))
+                            .collect();
+                        if open.is_empty() { break; }
+                        pos = open[(roll(vein_seed, step as i32, 0, 2) * open.len() as f64) as usize];
+                    }
+                }
+            }
+        }
+        mode => {
+            let mut placer = Placer::new(gen, ctx, seed, report);
+            placer.per_voxel   = true;
+            placer.min_spacing = spacing_3d(gen, &mode, candidates.len(), limit);
+            for &pos in &candidates {
+                if placer.report.placed >= limit { break; }
+                placer.try_place((pos, (0, 0, 0)), all, placed_hash);
+            }
+            return placer.report;
+        }
+    }
+    report
+}
+
+/// `min_spacing`, widened to the Poisson radius (default derived from the
+/// number of candidates and the requested count).
+fn spacing_3d(gen: &GeneratorDecl, mode: &Mode, candidates: usize, limit: usize) -> f64 {
+    let spacing = prop_f64(gen, "min_spacing", 0.0);
+    match mode {
+        Mode::Poisson { radius } => spacing.max(radius.unwrap_or_else(|| {
+            (candidates as f64 / limit.max(1) as f64).cbrt() * 0.75
+        })),
+        _ => spacing,
+    }
+}
+
+/// A voxel and the outward normal of the face an instance grows from.
+type Spot = ((i32, i32, i32), (i32, i32, i32));
+
+/// The normal of a column top.
+const UP: (i32, i32, i32) = (0, 1, 0);
+
+// ── Face scatter ───────────────────────────────────────────────────────────
+//
+// `surface = side | bottom | any` takes every filled voxel of the `over`
+// surfaces with an empty neighbour in one of the allowed directions.  A voxel
+// exposed on several allowed faces is a candidate once per face.  `where` is
+// evaluated at the voxel itself; spacing is 3D, as for volume scatter, and
+// only `uniform` and `poisson` modes apply.
+
+fn run_face_generator(
+    gen:         &GeneratorDecl,
+    ctx:         &GenCtx,
+    faces:       &[(i32, i32, i32)],
+    all:         &mut Vec<PlacedInstance>,
+    placed_hash: &mut SpatialHash,
+) -> GeneratorReport {
+    let seed      = prop_i64(gen, "seed",      42) as u64;
+    let sea_level = prop_i64(gen, "sea_level", 0)  as i32;
+    let condition = gen.props.iter().find(|p| p.key == "where").map(|p| &p.value);
+    let fields    = DistanceFields::new(ctx.surface, ctx.routes, sea_level);
+    let around    = around_columns(gen, all);
+
+    let mut solid: HashMap<(i32, i32, i32), u16> = HashMap::new();
+    for layer in ctx.layers {
+        let (ox, oy, oz) = layer.offset;
+        for (x, y, z, atom) in layer.grid.iter_filled() {
+            solid.insert((x as i32 + ox, y as i32 + oy, z as i32 + oz), atom);
+        }
+    }
+
+    let mut report = GeneratorReport::new(gen);
+    let mut candidates: Vec<Spot> = Vec::new();
+    for (&(x, y, z), atom) in &solid {
+        if around.as_ref().is_some_and(|cols| !cols.contains(&(x, z))) { continue; }
+        let open: Vec<(i32, i32, i32)> = faces.iter().copied()
+            .filter(|&(dx, dy, dz)| !solid.contains_key(&(x + dx, y + dy, z + dz)))
+            .collect();
+        report.candidates += open.len();
+        if open.is_empty() { continue; }
+        if ctx.avoid.atoms.contains(atom) {
+            report.rejected_avoid += open.len();
+            continue;
+        }
+
+        let eval = EvalCtx {
+            x, y, z, seed, sea_level,
+            surface:  ctx.surface,
+            host:     None,
+            routes:   ctx.routes,
+            fields:   &fields,
+            atom_ids: ctx.atom_ids,
+        };
+        if condition.is_some_and(|cond| !eval_bool(cond, &eval)) {
+            report.failed_where += open.len();
+            continue;
+        }
+        candidates.extend(open.into_iter().map(|n| ((x, y, z), n)));
+    }
+    candidates.sort_unstable();
+    shuffle(&mut candidates, seed);
+    let limit = instance_limit(gen, candidates.len());
 
-        placed.push(PlacedInstance {
-            generator_name: gen.name.name.clone(),
-            target_name:    gen.scatter_target.name.clone(),
+    let mut placer = Placer::new(gen, ctx, seed, report);
+    placer.per_voxel   = true;
+    placer.align       = align_to_normal(gen, true);
+    placer.min_spacing = spacing_3d(gen, &Mode::from_generator(gen), candidates.len(), limit);
+    for &spot in &candidates {
+        if placer.report.placed >= limit { break; }
+        placer.try_place(spot, all, placed_hash);
+    }
+    placer.report
+}
+
+/// How many instances to place from `candidates` eligible spots: `count`
+/// caps them, `density` asks for a share of the spots.  Lattices fill every
+/// point when neither is given; everything else defaults to 50.
+fn instance_limit(gen: &GeneratorDecl, candidates: usize) -> usize {
+    let count   = gen.props.iter().any(|p| p.key == "count").then(|| prop_i64(gen, "count", 50) as usize);
+    let density = gen.props.iter().any(|p| p.key == "density")
+        .then(|| (prop_f64(gen, "density", 0.0) * candidates as f64).round() as usize);
+    match (count, density) {
+        (Some(c), Some(d)) => c.min(d),
+        (Some(n), None) | (None, Some(n)) => n,
+        (None, None) if matches!(Mode::from_generator(gen), Mode::Grid { .. }) => usize::MAX,
+        (None, None) => 50,
+    }
+}
+
+// ── Scatter modes ──────────────────────────────────────────────────────────
+
+/// `mode = uniform | poisson(radius=…) | cluster(radius=…, per_cluster=…)
+///        | grid(spacing=…, jitter=…) | hex(spacing=…, jitter=…)`
+enum Mode {
+    /// Every eligible column in random order (the default).
+    Uniform,
+    /// Blue noise: no two instances closer than `radius` (default derived
+    /// from the area and the requested count).
+    Poisson { radius: Option<f64> },
+    /// Clumps of up to `per_cluster` instances within `radius` of a centre.
+    Cluster { radius: f64, per_cluster: usize },
+    /// A square (or, with `hex`, offset-row) lattice `spacing` apart, each
+    /// point moved by up to `jitter * spacing / 2`.
+    Grid { spacing: f64, jitter: f64, hex: bool },
+    /// Random-walk veins of `length` voxels through a host volume, forking
+    /// with probability `branch` per step.
+    Vein { length: usize, branch: f64 },
+}
+
+impl Mode {
+    fn from_generator(gen: &GeneratorDecl) -> Self {
+        let Some(prop) = gen.props.iter().find(|p| p.key == "mode") else { return Mode::Uniform };
+        let (name, args) = match &prop.value {
+            Expr::Ident(i)             => (i.name.as_str(), &[][..]),
+            Expr::Call { name, args, .. } => (name.as_str(), &args[..]),
+            _                          => return Mode::Uniform,
+        };
+        let arg = |key: &str| args.iter().find(|a| a.key == key).map(|a| expr_f64(&a.value));
+        match name {
+            "poisson" => Mode::Poisson { radius: arg("radius") },
+            "cluster" => Mode::Cluster {
+                radius:      arg("radius").unwrap_or(4.0),
+                per_cluster: arg("per_cluster").unwrap_or(5.0) as usize,
+            },
+            "grid" | "hex" => Mode::Grid {
+                spacing: arg("spacing").unwrap_or(8.0).max(1.0),
+                jitter:  arg("jitter").unwrap_or(0.0),
+                hex:     name == "hex",
+            },
+            "vein" => Mode::Vein {
+                length: arg("length").unwrap_or(12.0) as usize,
+                branch: arg("branch").unwrap_or(0.1),
+            },
+            _ => Mode::Uniform,
+        }
+    }
+}
+
+/// Lattice columns covering the candidates' bounding box, in world space so
+/// neighbouring generators with the same spacing line up.
+fn lattice(
+    candidates: &[(i32, i32, i32)],
+    spacing:    f64,
+    jitter:     f64,
+    hex:        bool,
+    seed:       u64,
+) -> Vec<(i32, i32)> {
+    if candidates.is_empty() { return Vec::new(); }
+    let (mut x0, mut z0, mut x1, mut z1) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+    for &(x, _, z) in candidates {
+        x0 = x0.min(x); x1 = x1.max(x);
+        z0 = z0.min(z); z1 = z1.max(z);
+    }
+
+    let row = if hex { spacing * 3f64.sqrt() / 2.0 } else { spacing };
+    let shake = jitter * spacing;
+    let mut points = Vec::new();
+    for j in (z0 as f64 / row).floor() as i32..=(z1 as f64 / row).ceil() as i32 {
+        let shift = if hex && j.rem_euclid(2) == 1 { spacing / 2.0 } else { 0.0 };
+        let i0 = ((x0 as f64 - shift) / spacing).floor() as i32;
+        let i1 = ((x1 as f64 - shift) / spacing).ceil() as i32;
+        for i in i0..=i1 {
+            let x = i as f64 * spacing + shift + shake * (roll(seed, i, j, 7) - 0.5);
+            let z = j as f64 * row + shake * (roll(seed, i, j, 8) - 0.5);
+            points.push((x.round() as i32, z.round() as i32));
+        }
+    }
+    points
+}
+
+// ── Placement checks ───────────────────────────────────────────────────────
+
+/// Accepts or rejects single spots for one generator: overlap with earlier
+/// instances, `avoid`, and `min_spacing`.
+struct Placer<'a> {
+    gen:           &'a GeneratorDecl,
+    ctx:           &'a GenCtx<'a>,
+    variation:     Variation,
+    seed:          u64,
+    min_spacing:   f64,
+    avoid_radius:  i32,
+    allow_overlap: bool,
+    /// `align = normal`: tilt instances on steep ground to face downhill,
+    /// or (per voxel) to their face.
+    align:         bool,
+    /// Spots are voxels (face or volume scatter) rather than column tops:
+    /// variation rolls per voxel and spacing is measured in 3D.
+    per_voxel:     bool,
+    /// Placed and rejected spots so far.
+    report:        GeneratorReport,
+}
+
+impl<'a> Placer<'a> {
+    fn new(gen: &'a GeneratorDecl, ctx: &'a GenCtx<'a>, seed: u64, report: GeneratorReport) -> Self {
+        Self {
+            gen, ctx, seed,
+            variation:     Variation::from_generator(gen),
+            min_spacing:   prop_f64(gen, "min_spacing",  0.0),
+            avoid_radius:  prop_f64(gen, "avoid_radius", 2.0).ceil() as i32,
+            allow_overlap: matches!(
+                gen.props.iter().find(|p| p.key == "overlap").map(|p| &p.value),
+                Some(Expr::Ident(i)) if i.name == "allow"
+            ),
+            align:     align_to_normal(gen, false),
+            per_voxel: false,
+            report,
+        }
+    }
+
+    /// Place an instance growing out of `spot` if nothing forbids it.
+    fn try_place(
+        &mut self,
+        ((x, y, z), normal): Spot,
+        all:         &mut Vec<PlacedInstance>,
+        placed_hash: &mut SpatialHash,
+    ) -> bool {
+        let ctx  = self.ctx;
+        let seed = if self.per_voxel { self.seed ^ hash(y as u64) } else { self.seed };
+        let pick = self.variation.pick(seed, x, z);
+        let up = match (self.align, self.per_voxel) {
+            (true, true) if normal != (0, 0, 0) => normal,
+            (true, false)                       => slope_up(&ctx.surface.elev, x, y, z),
+            _                                   => UP,
+        };
+        let rect = pick.footprint(ctx.footprints[pick.variant]).rect_at((x, y, z), normal, up);
+
+        // Boxes of earlier instances — never intersect them
+        if !self.allow_overlap {
+            for i in placed_hash.query(rect) {
+                if placed_hash.intersects(i, &rect) {
+                    self.report.rejected_overlap += 1;
+                    return false;
+                }
+            }
+        }
+
+        // Keep `avoid_radius` voxels clear of avoided entities / generators
+        if !ctx.avoid.instances.is_empty() {
+            let reach = rect.expand(self.avoid_radius);
+            let layers = &ctx.avoid.layers;
+            if layers.query(reach).into_iter().any(|i| layers.intersects(i, &reach)) {
+                self.report.rejected_avoid += 1;
+                return false;
+            }
+            for i in placed_hash.query(reach) {
+                let p = &all[i];
+                let avoided = ctx.avoid.instances.contains(&p.generator_name)
+                    || ctx.avoid.instances.contains(&p.target_name);
+                if avoided && placed_hash.intersects(i, &reach) {
+                    self.report.rejected_avoid += 1;
+                    return false;
+                }
+            }
+        }
+
+        // Centre-to-centre spacing between this generator's own instances.
+        // A sibling's box may sit one voxel off its spot, hence the extra 1.
+        if self.min_spacing > 0.0 {
+            let reach = Rect::column(x, z).expand(self.min_spacing.ceil() as i32 + 1);
+            for i in placed_hash.query(reach) {
+                let p = &all[i];
+                if p.generator_name != self.gen.name.name { continue; }
+                let dx = (x - p.x) as f64;
+                let dy = if self.per_voxel { (y - p.y) as f64 } else { 0.0 };
+                let dz = (z - p.z) as f64;
+                if (dx*dx + dy*dy + dz*dz).sqrt() < self.min_spacing {
+                    self.report.rejected_spacing += 1;
+                    return false;
+                }
+            }
+        }
+
+        placed_hash.insert(all.len(), rect);
+        all.push(PlacedInstance {
+            generator_name: self.gen.name.name.clone(),
+            target_name:    self.gen.scatter_targets[pick.variant].name.clone(),
+            variant:        pick.variant,
             x, y, z,
+            normal,
+            up,
+            rotation:       pick.rotation,
+            scale:          pick.scale,
+            jitter:         self.variation.jitter,
+            seed:           pick.seed,
         });
+        self.report.placed += 1;
+        true
+    }
+}
+
+// ── Variation ──────────────────────────────────────────────────────────────
+//
+// Each candidate column rolls its own variant, rotation, scale and jitter
+// seed from the generator seed, so a spot keeps its choice no matter how many
+// other spots were tried before it.
+
+/// `weights`, `rotate`, `scale` and `jitter` of one generator.
+struct Variation {
+    weights: Vec<f64>,
+    rotate:  Rotate,
+    scale:   (f64, f64),
+    jitter:  f64,
+}
+
+enum Rotate {
+    /// A fixed number of quarter turns (`rotate = 90`; default 0).
+    Fixed(i32),
+    /// `rotate = random_y90` — 0, 90, 180 or 270 degrees about y.
+    RandomY90,
+}
+
+/// The choices rolled for one candidate column.
+struct Pick {
+    variant:  usize,
+    rotation: i32,
+    scale:    f64,
+    seed:     u64,
+}
+
+impl Variation {
+    fn from_generator(gen: &GeneratorDecl) -> Self {
+        let prop = |key: &str| gen.props.iter().find(|p| p.key == key).map(|p| &p.value);
+        let n = gen.scatter_targets.len();
+
+        let weights = match prop("weights") {
+            Some(Expr::List(items)) if items.len() == n => items.iter().map(expr_f64).collect(),
+            _ => vec![1.0; n],
+        };
+        let rotate = match prop("rotate") {
+            Some(Expr::Ident(i)) if i.name == "random_y90" => Rotate::RandomY90,
+            Some(Expr::Int(deg)) => Rotate::Fixed((deg / 90) as i32),
+            _ => Rotate::Fixed(0),
+        };
+        let scale = match prop("scale") {
+            Some(Expr::Range { lo, hi }) => (expr_f64(lo), expr_f64(hi)),
+            Some(other) => (expr_f64(other), expr_f64(other)),
+            None => (1.0, 1.0),
+        };
+        let jitter = prop_f64(gen, "jitter", 0.0);
+
+        Self { weights, rotate, scale, jitter }
+    }
+
+    fn pick(&self, seed: u64, x: i32, z: i32) -> Pick {
+        let total: f64 = self.weights.iter().sum();
+        let mut r = roll(seed, x, z, 1) * total;
+        let mut variant = 0;
+        for (i, w) in self.weights.iter().enumerate() {
+            variant = i;
+            if r < *w { break; }
+            r -= w;
+        }
+
+        let rotation = match self.rotate {
+            Rotate::Fixed(turns) => turns,
+            Rotate::RandomY90    => (roll(seed, x, z, 2) * 4.0) as i32,
+        };
+        let (lo, hi) = self.scale;
+        let scale = lo + (hi - lo) * roll(seed, x, z, 3);
+
+        Pick { variant, rotation, scale, seed: hash(seed ^ column_key(x, z) ^ 4) }
+    }
+}
+
+impl Pick {
+    /// The variant's extent after rotation and scale.
+    fn footprint(&self, base: Footprint) -> Footprint {
+        let (w, d) = if self.rotation.rem_euclid(2) == 1 {
+            (base.depth, base.width)
+        } else {
+            (base.width, base.depth)
+        };
+        let size = |n: i32| ((n as f64 * self.scale).round() as i32).max(1);
+        Footprint { width: size(w), height: size(base.height), depth: size(d) }
+    }
+}
+
+/// A uniform value in [0, 1) for column (x, z), distinct per `salt`.
+fn roll(seed: u64, x: i32, z: i32, salt: u64) -> f64 {
+    let h = hash(seed ^ column_key(x, z) ^ salt.wrapping_mul(0x9e3779b97f4a7c15));
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn column_key(x: i32, z: i32) -> u64 {
+    hash(((x as u32 as u64) << 32) | z as u32 as u64)
+}
+
+/// A copy of `ent` whose numeric shape parameters are each scaled by a
+/// random factor in `1 ± amount`.  Noise seeds are left alone.
+pub fn jitter_entity(ent: &ResolvedEntity, amount: f64, seed: u64) -> ResolvedEntity {
+    let mut out = ent.clone();
+    let mut salt = 0u64;
+    for part in &mut out.parts {
+        if let Some(shape) = &mut part.shape {
+            jitter_shape(shape, amount, seed, &mut salt);
+        }
+    }
+    out
+}
+
+fn jitter_shape(shape: &mut ShapeExpr, amount: f64, seed: u64, salt: &mut u64) {
+    let args = match shape {
+        ShapeExpr::Box_        { args }
+        | ShapeExpr::Sphere    { args }
+        | ShapeExpr::Cylinder  { args }
+        | ShapeExpr::Cone      { args }
+        | ShapeExpr::Ellipsoid { args }
+        | ShapeExpr::Blob      { args }
+        | ShapeExpr::Heightfield { args } => args,
+        ShapeExpr::Shell   { inner, args }   => { jitter_shape(inner, amount, seed, salt); args }
+        ShapeExpr::Extrude { profile, args } => { jitter_shape(profile, amount, seed, salt); args }
+    };
+    for arg in args.iter_mut().filter(|a| a.key != "seed") {
+        *salt += 1;
+        let factor = 1.0 + amount * (2.0 * roll(seed, 0, 0, *salt) - 1.0);
+        arg.value = match arg.value {
+            Expr::Float(f) => Expr::Float(f * factor),
+            Expr::Int(n) if n > 0 => Expr::Int(((n as f64 * factor).round() as i64).max(1)),
+            ref other      => other.clone(),
+        };
+    }
+}
+
+// ── Spatial hash ───────────────────────────────────────────────────────────
+//
+// Uniform grid over XZ.  Each placed instance's box is registered in every
+// cell its columns touch, so a query only visits instances near the candidate
+// instead of every instance placed so far.
+
+/// Inclusive voxel box.  The spatial hash buckets it by its XZ columns.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Rect {
+    x0: i32, y0: i32, z0: i32,
+    x1: i32, y1: i32, z1: i32,
+}
+
+impl Rect {
+    /// The whole of column (x, z), at every height.
+    pub(crate) fn column(x: i32, z: i32) -> Rect {
+        Rect { x0: x, y0: i32::MIN / 2, z0: z, x1: x, y1: i32::MAX / 2, z1: z }
+    }
+
+    pub(crate) fn expand(self, by: i32) -> Rect {
+        Rect {
+            x0: self.x0 - by, y0: self.y0 - by, z0: self.z0 - by,
+            x1: self.x1 + by, y1: self.y1 + by, z1: self.z1 + by,
+        }
+    }
+
+    fn intersects(&self, other: &Rect) -> bool {
+        self.x0 <= other.x1 && other.x0 <= self.x1
+            && self.y0 <= other.y1 && other.y0 <= self.y1
+            && self.z0 <= other.z1 && other.z0 <= self.z1
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SpatialHash {
+    cell:  i32,
+    cells: HashMap<(i32,i32), Vec<usize>>,
+    /// Box of each instance, indexed like the generator output.
+    rects: Vec<Rect>,
+}
+
+impl SpatialHash {
+    pub(crate) fn new(cell: i32) -> Self {
+        Self { cell, cells: HashMap::new(), rects: Vec::new() }
+    }
+
+    fn cell_range(&self, rect: Rect) -> impl Iterator<Item = (i32, i32)> {
+        let (cx0, cz0) = (rect.x0.div_euclid(self.cell), rect.z0.div_euclid(self.cell));
+        let (cx1, cz1) = (rect.x1.div_euclid(self.cell), rect.z1.div_euclid(self.cell));
+        (cz0..=cz1).flat_map(move |cz| (cx0..=cx1).map(move |cx| (cx, cz)))
+    }
+
+    /// Register instance `idx`; indices must be inserted in order.
+    pub(crate) fn insert(&mut self, idx: usize, rect: Rect) {
+        debug_assert_eq!(idx, self.rects.len());
+        self.rects.push(rect);
+        for key in self.cell_range(rect).collect::<Vec<_>>() {
+            self.cells.entry(key).or_default().push(idx);
+        }
+    }
+
+    fn intersects(&self, idx: usize, rect: &Rect) -> bool {
+        self.rects[idx].intersects(rect)
+    }
+
+    /// Indices of instances registered in any cell `rect`'s columns touch.
+    pub(crate) fn query(&self, rect: Rect) -> Vec<usize> {
+        let mut out: Vec<usize> = self.cell_range(rect)
+            .filter_map(|key| self.cells.get(&key))
+            .flatten()
+            .copied()
+            .collect();
+        out.sort_unstable();
+        out.dedup();
+        out
     }
+}
+
+// ── Avoid ──────────────────────────────────────────────────────────────────
+
+/// What a generator's `avoid = …` names resolve to.
+///
+/// Atom and material names reject candidates whose surface voxel is that
+/// atom; entity and generator names reject candidates whose footprint comes
+/// within `avoid_radius` columns of one of their instances.  An avoided
+/// entity that is drawn on its own (a layer, or placed by `place` or
+/// `layout`) counts as an instance too, filling its bounding box.
+#[derive(Debug)]
+struct AvoidSpec {
+    atoms:     HashSet<u16>,
+    instances: HashSet<String>,
+    /// Boxes of the avoided entities' own layers.
+    layers:    SpatialHash,
+}
+
+impl AvoidSpec {
+    fn from_generator(gen: &GeneratorDecl, scene: &ResolvedScene, surfaces: &[Surface]) -> Self {
+        let mut spec = AvoidSpec { atoms: HashSet::new(), instances: HashSet::new(), layers: SpatialHash::new(16) };
+        let Some(prop) = gen.props.iter().find(|p| p.key == "avoid") else { return spec };
+
+        let names: Vec<String> = match &prop.value {
+            Expr::List(items) => items.iter().map(prop_str_val).collect(),
+            other             => vec![prop_str_val(other)],
+        };
+
+        for name in names {
+            if let Some(i) = scene.atoms.iter().position(|a| a.name == name) {
+                spec.atoms.insert(i as u16 + 1);
+            }
+            if let Some(m) = scene.materials.iter().find(|m| m.name == name) {
+                spec.atoms.insert(m.atom_index as u16 + 1);
+            }
+            if scene.entities.iter().any(|e| e.name == name)
+                || scene.generators.iter().any(|g| g.name.name == name)
+            {
+                spec.instances.insert(name);
+            }
+        }
 
-    placed
+        let avoided = surfaces.iter().filter(|s| spec.instances.contains(s.name) && s.grid.filled_count() > 0);
+        for (i, layer) in avoided.enumerate() {
+            let b = BBox::from_grid(layer.grid).shifted(layer.offset);
+            spec.layers.insert(i, Rect { x0: b.min_x, y0: b.min_y, z0: b.min_z, x1: b.max_x, y1: b.max_y, z1: b.max_z });
+        }
+        spec
+    }
 }
 
 // ── Elevation map ──────────────────────────────────────────────────────────
 
+/// World-space top surface of one or more grids: for each (x, z) column the
+/// highest filled y and the atom found there.
+struct SurfaceMap {
+    elev:  HashMap<(i32,i32), i32>,
+    atoms: HashMap<(i32,i32), u16>,
+}
+
+impl SurfaceMap {
+    fn build(surfaces: &[Surface]) -> Self {
+        let mut elev  = HashMap::new();
+        let mut atoms = HashMap::new();
+        for s in surfaces {
+            let (ox, oy, oz) = s.offset;
+            for (x, y, z, atom) in s.grid.iter_filled() {
+                let key = (x as i32 + ox, z as i32 + oz);
+                let wy  = y as i32 + oy;
+                let top = elev.entry(key).or_insert(i32::MIN);
+                if wy > *top {
+                    *top = wy;
+                    atoms.insert(key, atom);
+                }
+            }
+        }
+        Self { elev, atoms }
+    }
+}
+
 /// For each (x,z) column, find the highest filled voxel y.
-fn build_elevation_map(grid: &VoxelGrid) -> HashMap<(i32,i32), i32> {
+pub fn build_elevation_map(grid: &VoxelGrid) -> HashMap<(i32,i32), i32> {
     let mut map: HashMap<(i32,i32), i32> = HashMap::new();
     for (x, y, z, _) in grid.iter_filled() {
         let (x, y, z) = (x as i32, y as i32, z as i32);
@@ -122,13 +1299,101 @@ fn build_elevation_map(grid: &VoxelGrid) -> HashMap<(i32,i32), i32> {
     map
 }
 
+// ── Distance fields ────────────────────────────────────────────────────────
+//
+// `distance_to_water` and `distance_to_edge` need the distance from every
+// column to the nearest source column.  Both are built at most once per
+// generator, and only if its `where` asks for them: Dijkstra over 8-neighbour
+// steps (1 or √2), which stays within a few percent of Euclidean distance.
+
+struct DistanceFields<'a> {
+    surface:   &'a SurfaceMap,
+    routes:    &'a [CarvedRoute],
+    sea_level: i32,
+    water:     OnceCell<HashMap<(i32,i32), f64>>,
+    edge:      OnceCell<HashMap<(i32,i32), f64>>,
+}
+
+impl<'a> DistanceFields<'a> {
+    fn new(surface: &'a SurfaceMap, routes: &'a [CarvedRoute], sea_level: i32) -> Self {
+        Self { surface, routes, sea_level, water: OnceCell::new(), edge: OnceCell::new() }
+    }
+
+    /// Water = columns at or below sea level, plus carved river beds.
+    fn water(&self) -> &HashMap<(i32,i32), f64> {
+        self.water.get_or_init(|| {
+            let mut sources: Vec<(i32,i32)> = self.surface.elev.iter()
+                .filter(|(_, &y)| y <= self.sea_level)
+                .map(|(&col, _)| col)
+                .collect();
+            for route in self.routes.iter().filter(|r| r.kind == CarveKind::River) {
+                sources.extend(route.cells.iter().filter(|c| self.surface.elev.contains_key(c)));
+            }
+            distance_field(self.surface, sources)
+        })
+    }
+
+    /// Edge = columns with a 4-neighbour outside the surface.
+    fn edge(&self) -> &HashMap<(i32,i32), f64> {
+        self.edge.get_or_init(|| {
+            let sources: Vec<(i32,i32)> = self.surface.elev.keys()
+                .filter(|&&(x, z)| {
+                    [(x+1,z),(x-1,z),(x,z+1),(x,z-1)].iter()
+                        .any(|n| !self.surface.elev.contains_key(n))
+                })
+                .copied()
+                .collect();
+            distance_field(self.surface, sources)
+        })
+    }
+}
+
+fn distance_field(surface: &SurfaceMap, mut sources: Vec<(i32,i32)>) -> HashMap<(i32,i32), f64> {
+    const STEPS: [(i32, i32); 8] = [
+        (1, 0), (-1, 0), (0, 1), (0, -1),
+        (1, 1), (1, -1), (-1, 1), (-1, -1),
+    ];
+
+    // Fixed point (1/1000 voxel) so distances can live in a heap
+    sources.sort_unstable();
+    let mut best: HashMap<(i32,i32), u64> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+    for col in sources {
+        best.insert(col, 0);
+        heap.push(Reverse((0u64, col)));
+    }
+
+    while let Some(Reverse((d, (x, z)))) = heap.pop() {
+        if d > best[&(x, z)] { continue; }
+        for (dx, dz) in STEPS {
+            let next = (x + dx, z + dz);
+            if !surface.elev.contains_key(&next) { continue; }
+            let step = if dx != 0 && dz != 0 { 1414 } else { 1000 };
+            let nd = d + step;
+            if best.get(&next).is_none_or(|&c| nd < c) {
+                best.insert(next, nd);
+                heap.push(Reverse((nd, next)));
+            }
+        }
+    }
+
+    best.into_iter().map(|(col, d)| (col, d as f64 / 1000.0)).collect()
+}
+
 // ── Condition evaluator ────────────────────────────────────────────────────
 
 struct EvalCtx<'a> {
     x: i32,
     y: i32,   // elevation
     z: i32,
-    elev_map: &'a HashMap<(i32,i32), i32>,
+    seed:      u64,
+    sea_level: i32,
+    surface:   &'a SurfaceMap,
+    /// Set when (x, y, z) is a voxel inside a host volume.
+    host:      Option<&'a Surface<'a>>,
+    routes:    &'a [CarvedRoute],
+    fields:    &'a DistanceFields<'a>,
+    atom_ids:  &'a HashMap<String, u16>,
 }
 
 /// Evaluate a boolean condition expression at a given (x,y,z) position.
@@ -166,13 +1431,60 @@ fn eval_f64(expr: &Expr, ctx: &EvalCtx) -> f64 {
         Expr::Int(n)   => *n as f64,
         Expr::Float(f) => *f,
         Expr::Ident(i) => match i.name.as_str() {
-            "elevation" => ctx.y as f64,
-            "x"         => ctx.x as f64,
-            "z"         => ctx.z as f64,
-            "slope"     => estimate_slope(ctx.x, ctx.z, ctx.elev_map),
-            "depth"     => -(ctx.y as f64), // below sea level
-            _           => 0.0,
+            "elevation"         => ctx.y as f64,
+            "height"            => (ctx.y - ctx.sea_level) as f64,
+            "sea_level"         => ctx.sea_level as f64,
+            // Below sea level — or, inside a volume, below the host's top
+            "depth"             => match ctx.host {
+                Some(_) => (ctx.surface.elev[&(ctx.x, ctx.z)] - ctx.y) as f64,
+                None    => (ctx.sea_level - ctx.y) as f64,
+            },
+            "material"          => host_atom(ctx, ctx.x, ctx.y, ctx.z) as f64,
+            "exposed"           => face_neighbours(ctx).filter(|&a| a == 0).count() as f64,
+            "x"                 => ctx.x as f64,
+            "z"                 => ctx.z as f64,
+            "slope"             => terrain_normal(ctx).slope,
+            "aspect"            => terrain_normal(ctx).aspect,
+            "curvature"         => curvature(ctx.x, ctx.z, &ctx.surface.elev),
+            "distance_to_water" => ctx.fields.water().get(&(ctx.x, ctx.z)).copied().unwrap_or(f64::INFINITY),
+            "distance_to_edge"  => ctx.fields.edge().get(&(ctx.x, ctx.z)).copied().unwrap_or(0.0),
+            // Atom ids compare equal to atom / material names below
+            "surface_material"  => ctx.surface.atoms[&(ctx.x, ctx.z)] as f64,
+            name => ctx.atom_ids.get(name).map(|&id| id as f64).unwrap_or(0.0),
         },
+        // `distance_to(Main)` — horizontal distance to a carved river or path
+        Expr::Call { name, positional, .. } if name == "distance_to" => {
+            match positional.first() {
+                Some(Expr::Ident(target)) => ctx.routes.iter()
+                    .find(|r| r.name == target.name)
+                    .map(|r| r.distance_to(ctx.x, ctx.z))
+                    .unwrap_or(f64::INFINITY),
+                _ => 0.0,
+            }
+        }
+        // `noise(scale=0.05, seed=3)` — smooth value noise in [0, 1]
+        Expr::Call { name, args, .. } if name == "noise" => {
+            let arg = |key: &str, default: f64| args.iter()
+                .find(|a| a.key == key)
+                .map(|a| eval_f64(&a.value, ctx))
+                .unwrap_or(default);
+            terrain_noise(ctx.x, ctx.z, arg("seed", 0.0) as u64, arg("scale", 0.1))
+        }
+        // `random()` — uniform in [0, 1), fixed per column and generator seed
+        // (inside a volume, fixed per voxel)
+        Expr::Call { name, .. } if name == "random" => {
+            let seed = if ctx.host.is_some() { ctx.seed ^ hash(ctx.y as u64) } else { ctx.seed };
+            roll(seed, ctx.x, ctx.z, 5)
+        }
+        // `adjacent(Water)` — face neighbours of this host voxel that are Water
+        Expr::Call { name, positional, .. } if name == "adjacent" => {
+            let target = match positional.first() {
+                Some(Expr::Ident(i)) if i.name == "air" => 0,
+                Some(Expr::Ident(i)) => ctx.atom_ids.get(&i.name).copied().unwrap_or(u16::MAX),
+                _ => return 0.0,
+            };
+            face_neighbours(ctx).filter(|&a| a == target).count() as f64
+        }
         Expr::BinOp { op, lhs, rhs } => {
             let l = eval_f64(lhs, ctx);
             let r = eval_f64(rhs, ctx);
@@ -180,7 +1492,7 @@ fn eval_f64(expr: &Expr, ctx: &EvalCtx) -> f64 {
                 BinOp::Add => l + r,
                 BinOp::Sub => l - r,
                 BinOp::Mul => l * r,
-                BinOp::Div => if r != 0.0 { l / r } else { 0.0 },
+                BinOp::Div if r != 0.0 => l / r,
                 _          => 0.0,
             }
         }
@@ -188,13 +1500,78 @@ fn eval_f64(expr: &Expr, ctx: &EvalCtx) -> f64 {
     }
 }
 
-/// Estimate slope at (x,z) as the max elevation difference to 4 neighbors.
-fn estimate_slope(x: i32, z: i32, map: &HashMap<(i32,i32), i32>) -> f64 {
+/// The host atom at world (x, y, z); 0 outside the host or for surface scatter.
+fn host_atom(ctx: &EvalCtx, x: i32, y: i32, z: i32) -> u16 {
+    match ctx.host {
+        Some(h) => h.grid.get(x - h.offset.0, y - h.offset.1, z - h.offset.2),
+        None    => 0,
+    }
+}
+
+/// Host atoms of the six face neighbours of (x, y, z).
+fn face_neighbours<'a>(ctx: &'a EvalCtx) -> impl Iterator<Item = u16> + 'a {
+    FACES.iter().map(move |&(dx, dy, dz)| host_atom(ctx, ctx.x + dx, ctx.y + dy, ctx.z + dz))
+}
+
+const FACES: [(i32, i32, i32); 6] = [
+    (1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1),
+];
+
+/// Slope and aspect of the plane fitted to a column's 3×3 neighbourhood.
+struct Normal {
+    /// Degrees from horizontal: 0 = flat, 90 = vertical.
+    slope:  f64,
+    /// Compass direction the slope faces (downhill), in degrees clockwise
+    /// from -z: 0 = -z, 90 = +x, 180 = +z, 270 = -x.  Flat ground is 0.
+    aspect: f64,
+}
+
+fn terrain_normal(ctx: &EvalCtx) -> Normal {
+    fitted_normal(&ctx.surface.elev, ctx.x, ctx.y, ctx.z)
+}
+
+fn fitted_normal(map: &HashMap<(i32, i32), i32>, x: i32, y: i32, z: i32) -> Normal {
+    // A missing neighbour reads as the centre height
+    let h = |x: i32, z: i32| *map.get(&(x, z)).unwrap_or(&y) as f64;
+
+    // Sobel-weighted gradient (Horn's method)
+    let gx = ((h(x+1,z-1) + 2.0*h(x+1,z) + h(x+1,z+1))
+            - (h(x-1,z-1) + 2.0*h(x-1,z) + h(x-1,z+1))) / 8.0;
+    let gz = ((h(x-1,z+1) + 2.0*h(x,z+1) + h(x+1,z+1))
+            - (h(x-1,z-1) + 2.0*h(x,z-1) + h(x+1,z-1))) / 8.0;
+
+    let slope = (gx*gx + gz*gz).sqrt().atan().to_degrees();
+    let aspect = if gx == 0.0 && gz == 0.0 {
+        0.0
+    } else {
+        // Downhill is -gradient; measure clockwise from -z
+        (-gx).atan2(gz).to_degrees().rem_euclid(360.0)
+    };
+    Normal { slope, aspect }
+}
+
+/// The axis an `align = normal` instance on a column top points along:
+/// straight up below 45°, otherwise the downhill direction snapped to the
+/// nearest horizontal axis.
+fn slope_up(map: &HashMap<(i32, i32), i32>, x: i32, y: i32, z: i32) -> (i32, i32, i32) {
+    let normal = fitted_normal(map, x, y, z);
+    if normal.slope < 45.0 { return (0, 1, 0); }
+    match (normal.aspect / 90.0).round() as i32 % 4 {
+        0 => (0, 0, -1),
+        1 => (1, 0, 0),
+        2 => (0, 0, 1),
+        _ => (-1, 0, 0),
+    }
+}
+
+/// Discrete Laplacian of elevation: positive in hollows and valleys,
+/// negative on ridges and peaks.
+fn curvature(x: i32, z: i32, map: &HashMap<(i32,i32), i32>) -> f64 {
     let center = *map.get(&(x, z)).unwrap_or(&0) as f64;
     let neighbors = [(x+1,z),(x-1,z),(x,z+1),(x,z-1)];
     neighbors.iter()
-        .map(|&(nx,nz)| (*map.get(&(nx,nz)).unwrap_or(&0) as f64 - center).abs())
-        .fold(0.0_f64, f64::max)
+        .map(|n| map.get(n).map(|&h| h as f64).unwrap_or(center) - center)
+        .sum()
 }
 
 // ── Helpers ────────────────────────────────────────────────────────────────
@@ -215,6 +1592,14 @@ fn prop_f64(gen: &GeneratorDecl, key: &str, default: f64) -> f64 {
     }).unwrap_or(default)
 }
 
+fn expr_f64(expr: &Expr) -> f64 {
+    match expr {
+        Expr::Float(f) => *f,
+        Expr::Int(n)   => *n as f64,
+        _              => 0.0,
+    }
+}
+
 fn prop_str_val(expr: &Expr) -> String {
     match expr {
         Expr::Ident(i) => i.name.clone(),
@@ -224,7 +1609,7 @@ fn prop_str_val(expr: &Expr) -> String {
 }
 
 /// Fisher-Yates shuffle using deterministic hash.
-fn shuffle<T>(v: &mut Vec<T>, seed: u64) {
+fn shuffle<T>(v: &mut [T], seed: u64) {
     let n = v.len();
     for i in (1..n).rev() {
         let j = (hash(i as u64 ^ seed) % (i as u64 + 1)) as usize;
@@ -239,4 +1624,309 @@ fn hash(mut x: u64) -> u64 {
     x = x.wrapping_mul(0xc4ceb9fe1a85ec53);
     x ^= x >> 33;
     x
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::compile;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::resolver::Resolver;
+
+    /// Two atoms (`G` = 1, `S` = 2), a one-voxel `Pebble` and a 3×3×3 `Rock`
+    /// to scatter, and names for the hand-built surfaces.
+    const PRELUDE: &str = "
+        atom G { color = green }
+        atom S { color = gray }
+        material Grass { color = green, voxel_atom = G }
+        material Stone { color = gray,  voxel_atom = S }
+        entity Pebble { part P { shape = box(width=0, height=0, depth=0), material = Stone } }
+        entity Rock   { part P { shape = box(width=2, height=2, depth=2), material = Stone } }
+        entity Ground { part P { shape = box(width=0, height=0, depth=0), material = Grass } }
+        entity Hut    { part P { shape = box(width=0, height=0, depth=0), material = Stone } }
+    ";
+
+    /// A `w`×`d` slab of grass, one voxel thick.
+    fn ground(w: u32, d: u32) -> VoxelGrid {
+        let mut grid = VoxelGrid::new(w, 1, d);
+        for z in 0..d as i32 {
+            for x in 0..w as i32 { grid.set(x, 0, z, 1); }
+        }
+        grid
+    }
+
+    /// Run the generators in `src` over `surfaces`, with `Ground` as the terrain.
+    fn run_over(src: &str, surfaces: &[Surface], routes: &[CarvedRoute]) -> GeneratorOutput {
+        let src = format!("{PRELUDE}{src}");
+        let (tokens, lex_errors) = Lexer::new(&src).tokenize();
+        let (doc, parse_errors) = Parser::new(tokens).parse();
+        let (scene, errors) = Resolver::new().resolve(doc);
+        assert!(lex_errors.is_empty() && parse_errors.is_empty() && errors.is_empty(),
+            "{lex_errors:?} {parse_errors:?} {errors:?}");
+        let (compiled, _) = compile(&scene);
+        run_generators(surfaces, Some("Ground"), &scene, &compiled, routes)
+    }
+
+    fn run(src: &str, grid: &VoxelGrid) -> GeneratorOutput {
+        run_over(src, &[Surface { name: "Ground", grid, offset: (0, 0, 0) }], &[])
+    }
+
+    #[test]
+    fn avoided_atoms_never_grow_instances() {
+        let mut grid = ground(10, 10);
+        for z in 0..10 {
+            for x in 0..5 { grid.set(x, 0, z, 2); }
+        }
+        let out = run("generator Moss { scatter Pebble count = 100 avoid = Stone }", &grid);
+
+        assert!(out.instances.iter().all(|i| i.x >= 5));
+        assert_eq!((out.reports[0].placed, out.reports[0].rejected_avoid), (50, 50));
+    }
+
+    #[test]
+    fn avoid_keeps_clear_of_earlier_instances_and_avoided_layers() {
+        let grid = ground(30, 30);
+        let mut hut = VoxelGrid::new(3, 3, 3);
+        hut.set(1, 1, 1, 2);
+        let out = run_over("
+            generator Rocks { scatter Rock mode = grid(spacing = 10) }
+            generator Moss  { scatter Pebble count = 900 avoid = [Rocks, Hut] avoid_radius = 2 }
+        ", &[
+            Surface { name: "Ground", grid: &grid, offset: (0, 0, 0) },
+            Surface { name: "Hut",    grid: &hut,  offset: (24, 1, 24) },
+        ], &[]);
+
+        let rocks: Vec<_> = out.instances.iter().filter(|i| i.generator_name == "Rocks").collect();
+        let moss:  Vec<_> = out.instances.iter().filter(|i| i.generator_name == "Moss").collect();
+        assert_eq!(rocks.len(), 9);
+        assert!(!moss.is_empty() && out.reports[1].rejected_avoid > 0);
+        for m in moss {
+            // Two columns clear of each rock's 3×3 box, and of the hut's only voxel
+            assert!(rocks.iter().all(|r| (m.x - r.x).abs().max((m.z - r.z).abs()) > 3), "{m:?}");
+            assert!((m.x - 25).abs().max((m.z - 25).abs()) > 2, "{m:?}");
+        }
+    }
+
+    #[test]
+    fn instances_keep_their_boxes_apart_unless_overlap_is_allowed() {
+        let grid = ground(12, 12);
+        let out = run("generator Rocks { scatter Rock count = 100 }", &grid);
+        let rocks = &out.instances;
+        assert!(out.reports[0].rejected_overlap > 0);
+        for (i, a) in rocks.iter().enumerate() {
+            for b in &rocks[i + 1..] {
+                assert!((a.x - b.x).abs() >= 3 || (a.z - b.z).abs() >= 3, "{a:?} {b:?}");
+            }
+        }
+
+        let out = run("generator Rocks { scatter Rock count = 100 overlap = allow }", &grid);
+        assert_eq!((out.reports[0].placed, out.reports[0].rejected_overlap), (100, 0));
+    }
+
+    #[test]
+    fn over_picks_surfaces_and_reports_the_missing_ones() {
+        let grid = ground(10, 10);
+        let mut roof = VoxelGrid::new(4, 1, 4);
+        for z in 0..4 {
+            for x in 0..4 { roof.set(x, 0, z, 2); }
+        }
+        let surfaces = [
+            Surface { name: "Ground", grid: &grid, offset: (0, 0, 0) },
+            Surface { name: "Hut",    grid: &roof, offset: (2, 5, 2) },
+        ];
+        let on_roof = |i: &PlacedInstance| (2..6).contains(&i.x) && (2..6).contains(&i.z);
+
+        let out = run_over("generator Vents { scatter Pebble count = 100 over = [Hut, Rock] }", &surfaces, &[]);
+        assert_eq!(out.reports[0].missing, ["Rock"]);
+        assert_eq!(out.reports[0].placed, 16);
+        assert!(out.instances.iter().all(|i| on_roof(i) && i.y == 5));
+
+        // Where surfaces stack, the highest voxel wins
+        let out = run_over("generator Moss { scatter Pebble count = 200 over = world }", &surfaces, &[]);
+        assert_eq!(out.reports[0].placed, 100);
+        assert!(out.instances.iter().all(|i| i.y == if on_roof(i) { 5 } else { 0 }));
+    }
+
+    #[test]
+    fn variation_rolls_weighted_variants_turns_and_sizes() {
+        let grid = ground(20, 20);
+        let src = "generator Mix {
+            scatter [Pebble, Rock] weights = [3, 1] count = 400 overlap = allow
+            rotate = random_y90 scale = 0.5..1.5 seed = 7
+        }";
+        let out = run(src, &grid);
+        let rocks = out.instances.iter().filter(|i| i.target_name == "Rock").count();
+        assert_eq!(out.instances.len(), 400);
+        assert!((50..150).contains(&rocks), "{rocks} rocks");
+        assert!(out.instances.iter().all(|i| (0..4).contains(&i.rotation) && (0.5..=1.5).contains(&i.scale)));
+        for turns in 0..4 {
+            assert!(out.instances.iter().any(|i| i.rotation == turns));
+        }
+
+        // The same seed makes the same choices
+        let again = run(src, &grid);
+        let picks = |o: &GeneratorOutput| o.instances.iter()
+            .map(|i| (i.x, i.z, i.variant, i.rotation, i.scale.to_bits()))
+            .collect::<Vec<_>>();
+        assert_eq!(picks(&out), picks(&again));
+
+        let out = run("generator Mix { scatter [Pebble, Rock] weights = [1, 0] count = 50 }", &grid);
+        assert!(out.instances.iter().all(|i| i.target_name == "Pebble"));
+    }
+
+    #[test]
+    fn where_reads_routes_water_edges_and_materials() {
+        let mut grid = ground(20, 20);
+        for z in 0..20 { grid.set(0, 0, z, 2); }
+        let creek = CarvedRoute::new("Creek".into(), CarveKind::River, (0..20).map(|z| (10, z)).collect());
+        let surfaces = [Surface { name: "Ground", grid: &grid, offset: (0, 0, 0) }];
+        let placed = |cond: &str| {
+            let src = format!("
+                river Creek {{ from = (10, 0), to = (10, 19), material = Grass }}
+                generator Moss {{ scatter Pebble count = 400 sea_level = -1 where = {cond} }}
+            ");
+            run_over(&src, &surfaces, std::slice::from_ref(&creek)).instances
+        };
+
+        let near = placed("distance_to(Creek) <= 2");
+        assert!(near.len() == 100 && near.iter().all(|i| (i.x - 10).abs() <= 2));
+        // The river is the only water above the sea
+        let wet = placed("distance_to_water < 1.5");
+        assert!(wet.len() == 60 && wet.iter().all(|i| (i.x - 10).abs() <= 1));
+        assert_eq!(placed("distance_to_edge < 1").len(), 76);
+        let stony = placed("surface_material == Stone and z >= 5");
+        assert!(stony.len() == 15 && stony.iter().all(|i| i.x == 0 && i.z >= 5));
+    }
+
+    #[test]
+    fn grid_and_hex_modes_fill_a_lattice() {
+        let grid = ground(20, 20);
+        let out = run("generator Posts { scatter Pebble mode = grid(spacing = 4) }", &grid);
+        assert_eq!(out.instances.len(), 25);
+        assert!(out.instances.iter().all(|i| i.x % 4 == 0 && i.z % 4 == 0));
+
+        // Every other row shifts by half the spacing
+        let out = run("generator Posts { scatter Pebble mode = hex(spacing = 4) }", &grid);
+        let mut rows: Vec<i32> = out.instances.iter().map(|i| i.z).collect();
+        rows.sort_unstable();
+        rows.dedup();
+        for (j, z) in rows.iter().enumerate() {
+            let shift = if j % 2 == 1 { 2 } else { 0 };
+            assert!(out.instances.iter().filter(|i| i.z == *z).all(|i| i.x % 4 == shift), "row {z}");
+        }
+    }
+
+    #[test]
+    fn poisson_cluster_and_density_modes() {
+        let grid = ground(20, 20);
+        let dist = |a: &PlacedInstance, b: &PlacedInstance| (((a.x - b.x).pow(2) + (a.z - b.z).pow(2)) as f64).sqrt();
+
+        let out = run("generator Moss { scatter Pebble count = 400 mode = poisson(radius = 3) }", &grid);
+        assert!(out.reports[0].rejected_spacing > 0);
+        for (i, a) in out.instances.iter().enumerate() {
+            assert!(out.instances[i + 1..].iter().all(|b| dist(a, b) >= 3.0));
+        }
+
+        // Each clump of four lies within one radius of its centre
+        let out = run("generator Moss { scatter Pebble count = 20 mode = cluster(radius = 3, per_cluster = 4) }", &grid);
+        assert_eq!(out.instances.len(), 20);
+        for a in &out.instances {
+            assert!(out.instances.iter().any(|b| !std::ptr::eq(a, b) && dist(a, b) < 6.0));
+        }
+
+        let out = run("generator Moss { scatter Pebble density = 0.1 }", &grid);
+        assert_eq!(out.instances.len(), 40);
+    }
+
+    #[test]
+    fn children_run_after_their_parents_in_a_ring_around_them() {
+        let grid = ground(30, 30);
+        // Declared first, but it needs the rocks placed
+        let out = run("
+            generator Pebbles { scatter Pebble count = 500 around = Rocks radius = 2..3 }
+            generator Rocks   { scatter Rock mode = grid(spacing = 10) }
+        ", &grid);
+        assert_eq!((out.reports[0].name.as_str(), out.reports[1].name.as_str()), ("Rocks", "Pebbles"));
+
+        let rocks: Vec<_> = out.instances.iter().filter(|i| i.generator_name == "Rocks").collect();
+        let pebbles: Vec<_> = out.instances.iter().filter(|i| i.generator_name == "Pebbles").collect();
+        assert!(!pebbles.is_empty());
+        for p in pebbles {
+            let ring = rocks.iter().any(|r| {
+                let d = (((p.x - r.x).pow(2) + (p.z - r.z).pow(2)) as f64).sqrt();
+                (2.0..=3.0).contains(&d)
+            });
+            assert!(ring, "{p:?}");
+        }
+    }
+
+    #[test]
+    fn volume_scatter_embeds_instances_and_grows_veins_inside_the_host() {
+        let grid = ground(20, 20);
+        let mut block = VoxelGrid::new(6, 6, 6);
+        for (x, y, z) in (0..216).map(|i| (i % 6, i / 6 % 6, i / 36)) { block.set(x, y, z, 2); }
+        let surfaces = [
+            Surface { name: "Ground", grid: &grid,  offset: (0, 0, 0) },
+            Surface { name: "Hut",    grid: &block, offset: (10, 1, 10) },
+        ];
+        let inside = |(x, y, z): (i32, i32, i32)| (10..16).contains(&x) && (1..7).contains(&y) && (10..16).contains(&z);
+
+        let out = run_over("generator Ore { scatter G inside = Hut mode = vein(length = 5, branch = 0) count = 3 }", &surfaces, &[]);
+        assert_eq!(out.reports[0].placed, 3);
+        assert!((3..=15).contains(&out.voxels.len()));
+        assert!(out.voxels.iter().all(|v| v.host == "Hut" && v.atom_id == 1 && inside((v.x, v.y, v.z))));
+
+        let out = run_over("generator Gems { scatter Pebble inside = Hut count = 10 min_spacing = 3 }", &surfaces, &[]);
+        assert_eq!(out.instances.len(), 10);
+        for (i, a) in out.instances.iter().enumerate() {
+            assert!(a.normal == (0, 0, 0) && inside((a.x, a.y, a.z)));
+            for b in &out.instances[i + 1..] {
+                let d = ((a.x - b.x).pow(2) + (a.y - b.y).pow(2) + (a.z - b.z).pow(2)) as f64;
+                assert!(d.sqrt() >= 3.0, "{a:?} {b:?}");
+            }
+        }
+
+        // Only the 4×4×4 core has no open face
+        let out = run_over("generator Gems { scatter Pebble inside = Hut count = 100 where = exposed == 0 }", &surfaces, &[]);
+        assert_eq!(out.instances.len(), 64);
+        assert!(out.instances.iter().all(|i| inside((i.x - 1, i.y - 1, i.z - 1)) && inside((i.x + 1, i.y + 1, i.z + 1))));
+    }
+
+    #[test]
+    fn face_scatter_grows_out_of_open_faces_along_their_normals() {
+        let grid = ground(10, 10);
+        let mut pillar = VoxelGrid::new(1, 5, 1);
+        for y in 0..5 { pillar.set(0, y, 0, 2); }
+        let surfaces = [
+            Surface { name: "Ground", grid: &grid,   offset: (0, 0, 0) },
+            Surface { name: "Hut",    grid: &pillar, offset: (5, 1, 5) },
+        ];
+
+        let out = run_over("generator Vines { scatter Pebble over = Hut surface = side count = 100 }", &surfaces, &[]);
+        assert_eq!((out.reports[0].candidates, out.instances.len()), (20, 20));
+        for i in &out.instances {
+            let (nx, ny, nz) = i.normal;
+            assert!(ny == 0 && nx.abs() + nz.abs() == 1 && i.up == i.normal, "{i:?}");
+            assert_eq!((i.x, i.z), (5, 5));
+            // A one-voxel instance fills the open cell next to the face
+            assert_eq!(instance_origin(i, (1, 1, 1)), (5 + nx, i.y, 5 + nz));
+        }
+
+        let out = run_over("generator Drips { scatter Pebble over = Hut surface = bottom align = up }", &surfaces, &[]);
+        let drip = &out.instances[..];
+        assert!(matches!(drip, [d] if (d.y, d.normal, d.up) == (1, (0, -1, 0), (0, 1, 0))), "{drip:?}");
+    }
+
+    #[test]
+    fn reports_count_every_candidate_once() {
+        let mut grid = ground(10, 10);
+        for z in 0..10 { grid.set(0, 0, z, 2); }
+        let out = run("generator Moss { scatter Pebble count = 100 avoid = Stone where = x < 5 }", &grid);
+        let report = &out.reports[0];
+
+        assert_eq!((report.candidates, report.rejected_avoid, report.failed_where, report.placed), (100, 10, 50, 40));
+        assert!(report.fell_short());
+        assert_eq!(report.to_string(), "placed 40 of 100 from 100 candidates (50 failed `where`, 10 avoided)");
+    }
+}
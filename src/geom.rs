@@ -23,7 +23,7 @@ impl Axis {
 /// Normalize quarter-turns into [0..=3]
 #[inline]
 pub fn norm_turns(turns: i32) -> i32 {
-    ((turns % 4) + 4) % 4
+    turns.rem_euclid(4)
 }
 
 /// Rotate a point by 90° increments around an axis.
@@ -57,3 +57,17 @@ pub fn rotate_point_90(mut x: i32, mut y: i32, mut z: i32, axis: Axis, turns: i3
 
     (x, y, z)
 }
+
+/// The quarter-turn rotation that carries +y (an entity's "up") onto the
+/// axis-aligned unit vector `normal`.  `None` when no rotation is needed or
+/// `normal` is not a unit axis.
+pub fn tilt_to_normal(normal: (i32, i32, i32)) -> Option<(Axis, i32)> {
+    match normal {
+        (0, -1, 0) => Some((Axis::X, 2)),
+        (1, 0, 0)  => Some((Axis::Z, 3)),
+        (-1, 0, 0) => Some((Axis::Z, 1)),
+        (0, 0, 1)  => Some((Axis::X, 1)),
+        (0, 0, -1) => Some((Axis::X, 3)),
+        _          => None,
+    }
+}
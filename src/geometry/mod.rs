@@ -1,17 +1,19 @@
-use crate::ast::{ShapeExpr, NamedArg, Expr};
+use crate::ast::{DetailLevel, ShapeExpr, NamedArg, Expr};
 use crate::resolver::{ResolvedScene, ResolvedEntity};
+use crate::geom::Axis;
 use crate::voxel::VoxelGrid;
 
 // ── Public types ───────────────────────────────────────────────────────────
 
 /// A single compiled part — its own grid stamped at origin.
 /// The relation resolver will compute offsets between these.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CompiledPart {
     pub name:       String,
-    pub grid:       VoxelGrid,
+    pub grid:       VoxelGrid,          // at the world voxel size
     pub atom_id:    u16,
-    pub voxel_size: f64,
+    pub voxel_size: f64,                // size the part was stamped at
+    pub mirror_of:  Option<(String, Axis)>,
 }
 
 /// A fully compiled entity: individual part grids + the merged final grid.
@@ -20,24 +22,52 @@ pub struct CompiledEntity {
     pub name:       String,
     pub parts:      Vec<CompiledPart>,  // individual grids at origin
     pub grid:       VoxelGrid,          // merged grid (with offsets applied)
-    pub voxel_size: f64,
+    pub voxel_size: f64,                // entity's own size, before resampling
 }
 
 // ── Public entry point ─────────────────────────────────────────────────────
 
 /// Compile a resolved scene into voxel grids.
 /// Each entity gets a CompiledEntity with per-part grids ready for
-/// the relation resolver to offset.
-pub fn compile(scene: &ResolvedScene, voxel_size: f64) -> Vec<CompiledEntity> {
-    scene.entities.iter().map(|ent| {
-        let vs = ent.resolve.as_ref().map(|r| r.voxel_size).unwrap_or(voxel_size);
+/// the relation resolver to offset.  An entity's voxel size is its `resolve
+/// voxel_size` (or the world's declared one) times the multiplier of its
+/// detail level; its grids are then resampled onto the world voxel size,
+/// which the resolver has already refined to the finest detail asked for,
+/// so every entity merges at the same scale.
+///
+/// Also returns a warning for each entity whose own `resolve voxel_size` is
+/// finer than the world, whose resampling loses detail.
+pub fn compile(scene: &ResolvedScene) -> (Vec<CompiledEntity>, Vec<String>) {
+    let mut warnings = Vec::new();
+    let compiled = scene.entities.iter().map(|ent| {
+        let vs = ent.resolve.as_ref().and_then(|r| r.voxel_size).unwrap_or(scene.base_voxel_size);
+        let vs = vs * scene.detail.multiplier(scene.detail_of(&ent.name));
+        if vs < scene.voxel_size {
+            warnings.push(format!(
+                "entity '{}' is built at voxel size {vs} but the world's is {}; \
+                 resampling loses detail (set `world {{ resolve voxel_size = {vs} }}` to keep it)",
+                ent.name, scene.voxel_size,
+            ));
+        }
         compile_entity(ent, scene, vs)
-    }).collect()
+    }).collect();
+    (compiled, warnings)
 }
 
 // ── Entity compilation ─────────────────────────────────────────────────────
 
-fn compile_entity(ent: &ResolvedEntity, scene: &ResolvedScene, voxel_size: f64) -> CompiledEntity {
+/// Compile one entity.  `compile` calls this for every entity in the scene;
+/// generators call it again for instances whose parameters were jittered.
+///
+/// `voxel_size` already includes the entity's detail multiplier.  Parts
+/// refined to another level are stamped at that level's voxel size instead;
+/// `sketch` parts become bounding-box proxies.  Every part then goes onto
+/// the world voxel size: majority vote when it is finer, replication when
+/// it is coarser.
+pub fn compile_entity(ent: &ResolvedEntity, scene: &ResolvedScene, voxel_size: f64) -> CompiledEntity {
+    let level     = scene.detail_of(&ent.name);
+    let base_size = voxel_size / scene.detail.multiplier(level);
+
     // Step 1: compile each part into its own grid at origin
     let mut compiled_parts: Vec<CompiledPart> = Vec::new();
 
@@ -48,20 +78,60 @@ fn compile_entity(ent: &ResolvedEntity, scene: &ResolvedScene, voxel_size: f64)
             .unwrap_or(1);
 
         if let Some(shape) = &part.shape {
+            let part_level = scene.part_detail(&ent.name, &part.name).unwrap_or(level);
+            let part_size  = if part_level == level {
+                voxel_size
+            } else {
+                base_size * scene.detail.multiplier(part_level)
+            };
+
             let radius = bounding_radius(shape);
-            let half   = (radius / voxel_size).ceil() as i32 + 4;
+            let half   = (radius / part_size).ceil() as i32 + 4;
             let size   = (half * 2 + 1) as u32;
             let mut grid = VoxelGrid::new(size, size, size);
-            stamp(shape, half, half, half, atom_id, &mut grid, voxel_size);
+            stamp(shape, half, half, half, atom_id, &mut grid, part_size);
+
+            if part_level == DetailLevel::Sketch {
+                grid = bbox_proxy(&grid, atom_id);
+            }
+            grid = resampled(grid, part_size / scene.voxel_size);
+
             compiled_parts.push(CompiledPart {
                 name: part.name.clone(),
                 grid,
                 atom_id,
-                voxel_size,
+                voxel_size: part_size,
+                mirror_of: None,
             });
         }
     }
 
+    // `mirror_of` parts are flipped copies of their source's grid, so the
+    // two always match
+    for part in &ent.parts {
+        let Some((source, axis)) = &part.mirror_of else { continue };
+        let Some(src) = compiled_parts.iter().find(|p| &p.name == source) else { continue };
+        let atom_id = part.material_index
+            .and_then(|mi| scene.materials.get(mi))
+            .map(|mat| (mat.atom_index as u16) + 1)
+            .unwrap_or(src.atom_id);
+        let mut grid = src.grid.mirrored(*axis);
+        if atom_id != src.atom_id {
+            let filled: Vec<_> = grid.iter_filled().collect();
+            for (x, y, z, _) in filled {
+                grid.set(x as i32, y as i32, z as i32, atom_id);
+            }
+        }
+        let mirrored = CompiledPart {
+            name:       part.name.clone(),
+            grid,
+            atom_id,
+            voxel_size: src.voxel_size,
+            mirror_of:  Some((source.clone(), *axis)),
+        };
+        compiled_parts.push(mirrored);
+    }
+
     // Step 2: merge all parts into a combined grid at origin
     // (offsets are (0,0,0) until the relation resolver runs)
     let merged = merge_parts(&compiled_parts, &[]);
@@ -74,6 +144,39 @@ fn compile_entity(ent: &ResolvedEntity, scene: &ResolvedScene, voxel_size: f64)
     }
 }
 
+/// Resample `grid` by `factor` (source voxel size over target voxel size):
+/// majority vote below 1, replication above, untouched at 1.
+fn resampled(grid: VoxelGrid, factor: f64) -> VoxelGrid {
+    if factor < 1.0 {
+        grid.downsampled(factor)
+    } else if factor > 1.0 {
+        grid.scaled(factor)
+    } else {
+        grid
+    }
+}
+
+/// A grid of the same size with the bounding box of `grid`'s filled voxels
+/// filled solid — what `sketch` detail draws instead of the shape.
+fn bbox_proxy(grid: &VoxelGrid, atom_id: u16) -> VoxelGrid {
+    let (w, h, d) = grid.dims();
+    let mut out = VoxelGrid::new(w, h, d);
+    let (mut lo, mut hi) = ((u32::MAX, u32::MAX, u32::MAX), (0, 0, 0));
+    for (x, y, z, _) in grid.iter_filled() {
+        lo = (lo.0.min(x), lo.1.min(y), lo.2.min(z));
+        hi = (hi.0.max(x), hi.1.max(y), hi.2.max(z));
+    }
+    if lo.0 == u32::MAX { return out; }
+    for z in lo.2..=hi.2 {
+        for y in lo.1..=hi.1 {
+            for x in lo.0..=hi.0 {
+                out.set(x as i32, y as i32, z as i32, atom_id);
+            }
+        }
+    }
+    out
+}
+
 /// Merge compiled parts into a single VoxelGrid, applying (dx,dy,dz) offsets.
 /// `offsets` maps part name → (dx, dy, dz).  Missing entries default to (0,0,0).
 pub fn merge_parts(
@@ -390,7 +493,8 @@ fn stamp_heightfield(
 }
 
 /// 2D terrain noise: smooth pseudo-random in [0,1].
-fn terrain_noise(dx: i32, dz: i32, seed: u64, scale: f64) -> f64 {
+/// Also backs `noise(scale=…, seed=…)` in generator conditions.
+pub fn terrain_noise(dx: i32, dz: i32, seed: u64, scale: f64) -> f64 {
     // Sample at multiple octaves for natural-looking terrain
     let mut value  = 0.0f64;
     let mut amp    = 1.0f64;
@@ -425,6 +529,7 @@ fn hash_noise_2d(dx: i32, dz: i32, seed: u64) -> f64 {
 // Fill the outer shape, then hollow out by over-writing with air (0)
 // a smaller version of the same shape inset by `inner_offset` voxels.
 
+#[allow(clippy::too_many_arguments)]
 fn stamp_shell(
     inner_shape: &ShapeExpr,
     args:        &[NamedArg],
@@ -482,6 +587,7 @@ fn scale_args(args: &[NamedArg], keys: &[&str], delta: f64) -> Vec<NamedArg> {
 // Extrude a 2D profile shape upward by `height` voxels.
 // We take an XZ cross-section of the profile at y=0 and repeat it vertically.
 
+#[allow(clippy::too_many_arguments)]
 fn stamp_extrude(
     profile:    &ShapeExpr,
     args:       &[NamedArg],
@@ -559,10 +665,139 @@ pub fn grid_center(grid: &VoxelGrid) -> (i32, i32, i32) {
     let mut min_z = i32::MAX; let mut max_z = i32::MIN;
     for (x, y, z, _) in grid.iter_filled() {
         let (x, y, z) = (x as i32, y as i32, z as i32);
-        if x < min_x { min_x = x; } if x > max_x { max_x = x; }
-        if y < min_y { min_y = y; } if y > max_y { max_y = y; }
-        if z < min_z { min_z = z; } if z > max_z { max_z = z; }
+        min_x = min_x.min(x); max_x = max_x.max(x);
+        min_y = min_y.min(y); max_y = max_y.max(y);
+        min_z = min_z.min(z); max_z = max_z.max(z);
     }
     if min_x == i32::MAX { return (0, 0, 0); }
     ((min_x + max_x) / 2, (min_y + max_y) / 2, (min_z + max_z) / 2)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::relation_resolver::BBox;
+    use crate::resolver::Resolver;
+
+    /// A ball with a box beside it, compiled under `tail` (prints, refines,
+    /// detail tables).
+    fn build(tail: &str) -> (ResolvedScene, Vec<CompiledEntity>, Vec<String>) {
+        let src = format!("
+            atom A {{ color = gray }}
+            material M {{ color = gray, voxel_atom = A }}
+            entity Ball {{
+                part Core {{ shape = sphere(radius=4), material = M }}
+                part Tag  {{ shape = box(width=2, height=2, depth=2), material = M }}
+                resolve voxel_size = 1
+            }}
+            {tail}
+        ");
+        let (tokens, lex_errors) = Lexer::new(&src).tokenize();
+        let (doc, parse_errors) = Parser::new(tokens).parse();
+        let (scene, errors) = Resolver::new().resolve(doc);
+        assert!(lex_errors.is_empty() && parse_errors.is_empty() && errors.is_empty(),
+            "{lex_errors:?} {parse_errors:?} {errors:?}");
+        let (compiled, warnings) = compile(&scene);
+        (scene, compiled, warnings)
+    }
+
+    /// Filled voxels and filled width along x of one part.
+    fn part(compiled: &[CompiledEntity], name: &str) -> (usize, u32) {
+        let grid = &compiled[0].parts.iter().find(|p| p.name == name).unwrap().grid;
+        let xs: Vec<u32> = grid.iter_filled().map(|(x, ..)| x).collect();
+        (xs.len(), xs.iter().max().unwrap() - xs.iter().min().unwrap() + 1)
+    }
+
+    #[test]
+    fn detail_levels_refine_the_world_instead_of_averaging() {
+        let (scene, compiled, warnings) = build("print Ball");
+        assert_eq!(scene.voxel_size, 1.0);
+        assert_eq!(part(&compiled, "Core"), (257, 9));
+
+        // High detail draws the same 8-unit ball with 4× the voxels per side
+        let (scene, compiled, warnings_high) = build("print Ball detail=high");
+        assert_eq!((scene.voxel_size, scene.base_voxel_size), (0.25, 1.0));
+        let (count, width) = part(&compiled, "Core");
+        assert_eq!(width, 33);
+        assert!(count > 257 * 40, "{count}");
+        assert!(warnings.is_empty() && warnings_high.is_empty(), "{warnings:?} {warnings_high:?}");
+    }
+
+    #[test]
+    fn coarser_entities_are_replicated_onto_a_refined_world() {
+        let src = "
+            entity Crate { part P { shape = box(width=2, height=2, depth=2), material = M } resolve voxel_size = 1 }
+            print Ball detail=medium
+            print Crate
+        ";
+        let (scene, compiled, warnings) = build(src);
+        assert_eq!(scene.voxel_size, 0.5);
+        // Crate is built at 1.0 (3 voxels a side) and doubled
+        let crate_grid = &compiled[1].grid;
+        assert_eq!(crate_grid.iter_filled().count(), 6 * 6 * 6);
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn refined_parts_keep_their_detail() {
+        let (scene, compiled, warnings) = build("print Ball\nrefine Ball.Core detail=medium");
+        assert_eq!(scene.voxel_size, 0.5);
+        assert_eq!(part(&compiled, "Core").1, 17);
+        // The unrefined box is replicated: 3 voxels a side become 6
+        assert_eq!(part(&compiled, "Tag"), (6 * 6 * 6, 6));
+        assert!(warnings.is_empty(), "{warnings:?}");
+
+        // A later `refine` of the same part wins
+        let (scene, ..) = build("print Ball\nrefine Ball.Core detail=high\nrefine Ball.Core detail=low");
+        assert_eq!(scene.voxel_size, 1.0);
+    }
+
+    #[test]
+    fn sketch_parts_are_solid_bounding_boxes() {
+        let (scene, compiled, _) = build("print Ball detail=sketch");
+        assert_eq!(scene.voxel_size, 1.0);
+        assert_eq!(part(&compiled, "Core"), (9 * 9 * 9, 9));
+
+        // …except parts refined back to a real level
+        let (_, compiled, _) = build("print Ball detail=sketch\nrefine Ball.Core detail=low");
+        assert_eq!(part(&compiled, "Core"), (257, 9));
+    }
+
+    #[test]
+    fn entities_at_different_voxel_sizes_merge_at_the_same_world_size() {
+        let (scene, compiled, warnings) = build("
+            entity Fine   { part P { shape = sphere(radius=4), material = M } resolve voxel_size = 0.5 }
+            entity Coarse { part P { shape = sphere(radius=4), material = M } resolve voxel_size = 1 }
+            world W { resolve voxel_size = 0.5 }
+            print Fine
+            print Coarse
+        ");
+        assert!(warnings.is_empty(), "{warnings:?}");
+        let extent = |name: &str| {
+            let ent = compiled.iter().find(|e| e.name == name).unwrap();
+            let b = BBox::from_grid(&ent.grid);
+            let world = |n: i32| n as f64 * scene.voxel_size;
+            (world(b.width()), world(b.height()), world(b.depth()))
+        };
+        // Both balls are 8 units across; each grid rounds to its own voxels,
+        // so they agree to within one of the coarser voxel
+        let (fine, coarse) = (extent("Fine"), extent("Coarse"));
+        assert_eq!(fine, (8.5, 8.5, 8.5));
+        for (f, c) in [(fine.0, coarse.0), (fine.1, coarse.1), (fine.2, coarse.2)] {
+            assert!((f - c).abs() <= 1.0, "{fine:?} {coarse:?}");
+        }
+    }
+
+    #[test]
+    fn entities_finer_than_the_world_still_warn() {
+        let (scene, _, warnings) = build("
+            entity Pebble { part P { shape = sphere(radius=1), material = M } resolve voxel_size = 0.5 }
+            print Pebble
+        ");
+        assert_eq!(scene.voxel_size, 1.0);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("'Pebble'"), "{warnings:?}");
+    }
+}
@@ -11,6 +11,8 @@ pub struct Lexer<'src> {
     chars: std::iter::Peekable<std::str::CharIndices<'src>>,
     line: usize,
     col: usize,
+    /// True until the first non-whitespace character on the current line.
+    line_start: bool,
     errors: Vec<MoxiError>,
 }
 
@@ -20,6 +22,7 @@ impl<'src> Lexer<'src> {
             chars: src.char_indices().peekable(),
             line: 1,
             col: 1,
+            line_start: true,
             errors: Vec::new(),
         }
     }
@@ -53,8 +56,10 @@ impl<'src> Lexer<'src> {
         if ch == '\n' {
             self.line += 1;
             self.col = 1;
+            self.line_start = true;
         } else {
             self.col += 1;
+            if !ch.is_whitespace() { self.line_start = false; }
         }
         Some(ch)
     }
@@ -75,8 +80,14 @@ impl<'src> Lexer<'src> {
             match self.peek() {
                 Some(' ') | Some('\t') | Some('\r') => { self.advance(); }
                 Some('\n') => { self.advance(); }
-                // Line comments: # … and > … (Markdown blockquote)
-                Some('#') | Some('>') => {
+                // Line comments: # … anywhere, > … (Markdown blockquote) only
+                // at the start of a line so `elevation > 3` still lexes.
+                Some('#') => {
+                    while self.peek().is_some() && self.peek() != Some('\n') {
+                        self.advance();
+                    }
+                }
+                Some('>') if self.line_start => {
                     while self.peek().is_some() && self.peek() != Some('\n') {
                         self.advance();
                     }
@@ -122,7 +133,7 @@ impl<'src> Lexer<'src> {
             if c.is_ascii_digit() {
                 raw.push(c);
                 self.advance();
-            } else if c == '.' && !is_float && self.peek2().map_or(false, |c2| c2.is_ascii_digit()) {
+            } else if c == '.' && !is_float && self.peek2().is_some_and(|c2| c2.is_ascii_digit()) {
                 is_float = true;
                 raw.push(c);
                 self.advance();
@@ -182,6 +193,8 @@ impl<'src> Lexer<'src> {
             "avoid"      => TokenKind::Avoid,
             "parts"      => TokenKind::Parts,
             "attach"     => TokenKind::Attach,
+            "place"      => TokenKind::Place,
+            "layout"     => TokenKind::Layout,
 
             // Built-in shapes
             "box"        => TokenKind::Box_,
@@ -242,7 +255,7 @@ impl<'src> Lexer<'src> {
             '/' => TokenKind::Slash,
 
             '-' => {
-                if self.peek().map_or(false, |c| c.is_ascii_digit()) {
+                if self.peek().is_some_and(|c| c.is_ascii_digit()) {
                     // Negative number literal
                     let first = self.advance().unwrap();
                     let inner = self.read_number(first);
@@ -304,4 +317,31 @@ impl<'src> Lexer<'src> {
 
         Token::new(kind, span)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(src: &str) -> Vec<TokenKind> {
+        let (tokens, errors) = Lexer::new(src).tokenize();
+        assert!(errors.is_empty(), "{errors:?}");
+        tokens.into_iter().map(|t| t.kind).collect()
+    }
+
+    #[test]
+    fn quote_lines_are_comments_but_greater_than_is_not() {
+        let src = "> a note\nwhere = elevation > 3\n    > an indented note\n";
+        assert_eq!(kinds(src), [
+            TokenKind::Where, TokenKind::Eq, TokenKind::Ident("elevation".into()),
+            TokenKind::Gt, TokenKind::Int(3), TokenKind::Eof,
+        ]);
+    }
+
+    #[test]
+    fn river_and_path_are_plain_identifiers() {
+        assert_eq!(kinds("river path"), [
+            TokenKind::Ident("river".into()), TokenKind::Ident("path".into()), TokenKind::Eof,
+        ]);
+    }
+}
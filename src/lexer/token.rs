@@ -45,6 +45,8 @@ pub enum TokenKind {
     Avoid,
     Parts,
     Attach,
+    Place,
+    Layout,
 
     // ── Built-in shape names ───────────────────────────────────────────────
     Box_,        // `box` is a Rust keyword, trailing underscore
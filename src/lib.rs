@@ -12,4 +12,6 @@ pub mod types;
 pub mod export;
 pub mod bevy_viewer;
 pub mod relation_resolver;
-pub mod generator;
\ No newline at end of file
+pub mod generator;
+pub mod carve;
+pub mod analysis;
\ No newline at end of file
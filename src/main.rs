@@ -1,13 +1,19 @@
 use clap::{Parser, Subcommand};
 use moxi_lib::lexer::Lexer;
 use moxi_lib::parser::Parser as MoxiParser;
-use moxi_lib::resolver::Resolver;
-use moxi_lib::geometry::{self, merge_parts};
-use moxi_lib::relation_resolver::resolve_offsets;
-use moxi_lib::generator::run_generators;
+use moxi_lib::resolver::{FloatingPolicy, Resolver};
+use moxi_lib::geometry;
+use moxi_lib::relation_resolver::{assemble_entity, layout_offset, solve_order, span_route};
+use moxi_lib::ast::RelationStmt;
+use moxi_lib::generator::{instance_grid, instance_origin, run_generators, GeneratorOutput, GeneratorReport, Surface};
+use moxi_lib::carve::{carve_routes, kind_label};
 use moxi_lib::types::{grid_to_scene, Voxel, VoxelScene};
 use moxi_lib::export::export_to_obj;
-use moxi_lib::ast::TopLevel;
+use moxi_lib::geom::Axis;
+use moxi_lib::analysis::{
+    check_bounds, check_floating, check_overlaps, entity_stats, floating_pieces, mark_overlaps,
+    settle_entity, settle_floating, FloatingReport, MassProps,
+};
 
 // ── CLI definition ─────────────────────────────────────────────────────────
 
@@ -32,6 +38,14 @@ enum Command {
         /// Output directory (default: output/)
         #[arg(short, long, default_value = "output")]
         out: String,
+
+        /// Report where each generator's candidate spots went
+        #[arg(long)]
+        explain_generators: bool,
+
+        /// Draw cells where an entity's parts overlap in magenta
+        #[arg(long)]
+        show_overlaps: bool,
     },
 
     /// Compile and open the 3D viewer
@@ -45,6 +59,12 @@ enum Command {
         /// Path to the .md script
         script: String,
     },
+
+    /// Print volume, mass, centre of mass and inertia of every entity
+    Stats {
+        /// Path to the .md script
+        script: String,
+    },
 }
 
 // ── Entry point ────────────────────────────────────────────────────────────
@@ -53,15 +73,15 @@ fn main() {
     let cli = Cli::parse();
 
     match cli.command {
-        Command::Compile { script, out } => {
+        Command::Compile { script, out, explain_generators, show_overlaps } => {
             let source = read_script(&script);
             let scene  = compile_scene(&source, &script);
-            run_export(&scene, &out);
+            run_export(&scene, &out, explain_generators, show_overlaps);
         }
         Command::View { script } => {
             let source = read_script(&script);
             let scene  = compile_scene(&source, &script);
-            let voxel_scene = build_world_scene(&scene);
+            let voxel_scene = build_world_scene(&scene, false, false);
 
             #[cfg(feature = "viewer")]
             moxi_lib::bevy_viewer::view_voxels_bevy(voxel_scene);
@@ -76,6 +96,11 @@ fn main() {
             let source = read_script(&script);
             check_only(&source, &script);
         }
+        Command::Stats { script } => {
+            let source = read_script(&script);
+            let scene  = compile_scene(&source, &script);
+            print_stats(&scene);
+        }
     }
 }
 
@@ -96,7 +121,6 @@ fn read_script(path: &str) -> String {
 struct CompiledScene {
     resolved:   moxi_lib::resolver::ResolvedScene,
     compiled:   Vec<moxi_lib::geometry::CompiledEntity>,
-    generators: Vec<moxi_lib::ast::GeneratorDecl>,
 }
 
 fn compile_scene(source: &str, path: &str) -> CompiledScene {
@@ -106,11 +130,6 @@ fn compile_scene(source: &str, path: &str) -> CompiledScene {
     let (doc, parse_errors) = MoxiParser::new(tokens).parse();
     for e in &parse_errors { eprintln!("[parse] {e}"); }
 
-    // Extract generators before consuming doc
-    let generators: Vec<_> = doc.items.iter().filter_map(|item| {
-        if let TopLevel::GeneratorDecl(g) = item { Some(g.clone()) } else { None }
-    }).collect();
-
     let (resolved, resolve_errors) = Resolver::new().resolve(doc);
     for e in &resolve_errors { eprintln!("[resolve] {e}"); }
 
@@ -120,130 +139,279 @@ fn compile_scene(source: &str, path: &str) -> CompiledScene {
         std::process::exit(1);
     }
 
-    let compiled = geometry::compile(&resolved, 1.0);
+    let (compiled, warnings) = geometry::compile(&resolved);
+    for w in &warnings { eprintln!("warning: {w}"); }
+
+    let (mut violations, warnings) = check_overlaps(&compiled, &resolved);
+    for w in &warnings { eprintln!("warning: {w}"); }
+    let floating = check_floating(&compiled, &resolved);
+    violations.extend(floating.iter().filter_map(FloatingReport::violation));
+    violations.extend(check_bounds(&compiled, &resolved));
+    for e in &violations { eprintln!("[constraint] {e}"); }
+    if !violations.is_empty() {
+        eprintln!("{} error(s) in '{path}' — aborting", violations.len());
+        std::process::exit(1);
+    }
 
     println!("✓ compiled '{path}'");
+    for report in &floating { print_floating(report); }
     for ent in &compiled {
         println!("  entity '{}' — {} parts", ent.name, ent.parts.len());
     }
 
-    CompiledScene { resolved, compiled, generators }
+    CompiledScene { resolved, compiled }
 }
 
 // ── World scene builder ────────────────────────────────────────────────────
 //
-// Finds a terrain entity, runs generators, merges everything into one
-// VoxelScene.  Falls back to rendering all entities stacked if no terrain
-// is found (e.g. skeleton.md has no generators).
+// Assembles every entity into a layer at the world origin, moves layers named
+// in `layout`, carves rivers/paths into the primary terrain, adds one layer
+// per `place` statement, runs generators over the layers they name with
+// `over`, and merges everything into one VoxelScene.
+
+/// A warning for floating pieces left in place, a note for removed or
+/// dropped ones.
+fn print_floating(report: &FloatingReport) {
+    match report.policy {
+        FloatingPolicy::Warn => eprintln!("warning: {report} (`resolve floating = remove | drop` to fix)"),
+        FloatingPolicy::Remove { max } if report.kept > 0 => eprintln!(
+            "warning: {report}; {} over {max} voxel(s) left in place (`remove(max = n)` or `drop` to fix)",
+            report.kept,
+        ),
+        FloatingPolicy::Remove { .. } => println!("  {report}, removed"),
+        FloatingPolicy::Drop  => println!("  {report}, dropped"),
+        FloatingPolicy::Error => {}
+    }
+}
 
-fn build_world_scene(scene: &CompiledScene) -> VoxelScene {
+/// One rendered entity grid and where it sits in the world.
+struct Layer<'a> {
+    name:   &'a str,
+    grid:   moxi_lib::voxel::VoxelGrid,
+    offset: (i32, i32, i32),
+}
+
+fn build_world_scene(scene: &CompiledScene, explain_generators: bool, show_overlaps: bool) -> VoxelScene {
+    let assemble = |ent: &moxi_lib::geometry::CompiledEntity, resolved: &moxi_lib::resolver::ResolvedEntity| {
+        let vs = scene.resolved.voxel_size;
+        let grid = if show_overlaps {
+            mark_overlaps(&ent.parts, &resolved.relations, vs)
+        } else {
+            assemble_entity(&ent.parts, &resolved.relations, vs)
+        };
+        settle_entity(grid, &ent.parts, resolved, vs)
+    };
     let mut all_voxels: Vec<Voxel> = Vec::new();
 
     // Generator target names — these are placed by generators, not directly
-    let generator_targets: std::collections::HashSet<&str> = scene.generators
-        .iter().map(|g| g.scatter_target.name.as_str()).collect();
+    let generator_targets: std::collections::HashSet<&str> = scene.resolved.generators
+        .iter().flat_map(|g| &g.scatter_targets).map(|t| t.name.as_str()).collect();
 
-    // Primary terrain = first entity with a heightfield part (generators run against it)
+    // Primary terrain = first entity with a heightfield part (rivers, paths and
+    // generators without `over` use it)
     let primary_terrain_name = scene.resolved.entities.iter().find(|e| {
         e.parts.iter().any(|p| matches!(&p.shape,
             Some(moxi_lib::ast::ShapeExpr::Heightfield { .. })
         ))
     }).map(|e| e.name.as_str());
 
-    let mut primary_terrain_grid = None;
-
-    // First pass: compile primary terrain to get its center offset
-    let mut terrain_center_offset: (i32, i32, i32) = (0, 0, 0);
-    if let Some(ref pname) = primary_terrain_name {
-        if let Some((ent, resolved_ent)) = scene.compiled.iter()
-            .zip(scene.resolved.entities.iter())
-            .find(|(e, _)| e.name.as_str() == *pname)
-        {
-            let offsets = resolve_offsets(&ent.parts, &resolved_ent.relations);
-            let offsets_vec: Vec<_> = offsets.iter()
-                .map(|(n,o)| (n.clone(),(o.dx,o.dy,o.dz))).collect();
-            let grid = merge_parts(&ent.parts, &offsets_vec);
-            let (w, _, d) = grid.dims();
-            terrain_center_offset = (-(w as i32 / 2), 0, -(d as i32 / 2));
-            primary_terrain_grid = Some(grid);
-        }
+    if primary_terrain_name.is_none() && !scene.resolved.carves.is_empty() {
+        eprintln!("warning: rivers/paths declared but no heightfield terrain found");
     }
 
-    // Render every entity in declaration order except generator targets
+    // Entities named by `place` only appear where they are placed
+    let placed_entities: std::collections::HashSet<&str> = scene.resolved.placements
+        .iter().map(|p| p.entity.as_str()).collect();
+
+    // Assemble every entity in declaration order except generator targets
+    let mut layers: Vec<Layer> = Vec::new();
+    let mut placed_grids: std::collections::HashMap<&str, moxi_lib::voxel::VoxelGrid> =
+        std::collections::HashMap::new();
     for (ent, resolved_ent) in scene.compiled.iter().zip(scene.resolved.entities.iter()) {
-        if generator_targets.contains(ent.name.as_str()) {
+        if placed_entities.contains(ent.name.as_str()) {
+            placed_grids.insert(ent.name.as_str(), assemble(ent, resolved_ent));
             continue;
         }
-
-        // Skip primary terrain — already compiled above
-        if Some(ent.name.as_str()) == primary_terrain_name.as_deref()
-            && primary_terrain_grid.is_some()
-        {
-            let grid = primary_terrain_grid.as_ref().unwrap();
-            println!("  layer '{}': {}x{}x{}, {} voxels",
-                ent.name, grid.dims().0, grid.dims().1, grid.dims().2,
-                grid.filled_count());
-            all_voxels.extend(
-                grid_to_scene(grid, &scene.resolved.atoms, terrain_center_offset).voxels
-            );
+        if generator_targets.contains(ent.name.as_str()) {
             continue;
         }
 
-        let offsets = resolve_offsets(&ent.parts, &resolved_ent.relations);
-        let offsets_vec: Vec<_> = offsets.iter()
-            .map(|(n,o)| (n.clone(),(o.dx,o.dy,o.dz))).collect();
-        let grid = merge_parts(&ent.parts, &offsets_vec);
+        let grid = assemble(ent, resolved_ent);
 
-        println!("  layer '{}': {}x{}x{}, {} voxels",
+        let print = scene.resolved.prints.iter().find(|p| p.target.name == ent.name);
+        let note = match print {
+            Some(p) => p.detail.as_ref().map(|d| format!(", detail={}", format!("{d:?}").to_lowercase())).unwrap_or_default(),
+            None => ", not printed".to_string(),
+        };
+        println!("  layer '{}': {}x{}x{}, {} voxels{note}",
             ent.name, grid.dims().0, grid.dims().1, grid.dims().2,
             grid.filled_count());
 
-        // Center this layer at world origin.
-        // For flat layers (ocean, sand) push them down so top face sits at y=0,
-        // leaving y>0 for terrain to grow into without being swallowed.
-        let (gw, gh, gd) = grid.dims();
-        let is_heightfield = resolved_ent.parts.iter().any(|p| matches!(&p.shape,
-            Some(moxi_lib::ast::ShapeExpr::Heightfield { .. })
-        ));
-        let y_off = if is_heightfield { 0 } else { -(gh as i32 - 1) };
-        let layer_offset = (-(gw as i32 / 2), y_off, -(gd as i32 / 2));
+        // The grid stays where it was built, voxel (0, 0, 0) at the world
+        // origin, unless `layout` moves it
+        layers.push(Layer { name: ent.name.as_str(), grid, offset: (0, 0, 0) });
+    }
 
-        all_voxels.extend(
-            grid_to_scene(&grid, &scene.resolved.atoms, layer_offset).voxels
+    // `layout` relations between entities, each after the entity it hangs
+    // from.  `across` a river or path waits until the route has been carved.
+    let is_route = |rel: &RelationStmt| scene.resolved.carves.iter().any(|c| c.name == rel.object.name);
+    let ordered = solve_order(&scene.resolved.layout, |r| r.subject.name.clone());
+    for rel in ordered.into_iter().flat_map(|(_, group)| group).filter(|r| !is_route(r)) {
+        let object = if rel.object.name == "terrain" { primary_terrain_name } else { Some(rel.object.name.as_str()) };
+        let Some(s) = layers.iter().position(|l| l.name == rel.subject.name) else {
+            eprintln!("warning: layout: '{}' is not drawn on its own", rel.subject.name);
+            continue;
+        };
+        let Some(o) = layers.iter().position(|l| Some(l.name) == object) else {
+            eprintln!("warning: layout: '{}' is not drawn on its own", rel.object.name);
+            continue;
+        };
+        layers[s].offset = layout_offset(
+            rel, &layers[s].grid, layers[s].offset, &layers[o].grid, layers[o].offset, scene.resolved.voxel_size,
         );
+        println!("  layout '{}' → {:?}", rel.subject.name, layers[s].offset);
     }
 
-    // Run generators against primary terrain
-    if !scene.generators.is_empty() {
-        if let Some(ref terrain_grid) = primary_terrain_grid {
-            let placements = run_generators(terrain_grid, &scene.generators);
-            println!("  placed {} instances", placements.len());
-
-            for placement in &placements {
-                if let Some(target_ent) = scene.compiled.iter()
-                    .find(|e| e.name == placement.target_name)
-                {
-                    let target_resolved = scene.resolved.entities.iter()
-                        .find(|e| e.name == target_ent.name).unwrap();
-
-                    let off = resolve_offsets(&target_ent.parts, &target_resolved.relations);
-                    let off_vec: Vec<_> = off.iter()
-                        .map(|(n,o)| (n.clone(),(o.dx,o.dy,o.dz))).collect();
-                    let grid = merge_parts(&target_ent.parts, &off_vec);
-
-                    let (tw, _, td) = grid.dims();
-                    let world_off = (
-                        placement.x - tw as i32 / 2 + terrain_center_offset.0,
-                        placement.y + 1,
-                        placement.z - td as i32 / 2 + terrain_center_offset.2,
-                    );
-                    all_voxels.extend(
-                        grid_to_scene(&grid, &scene.resolved.atoms, world_off).voxels
-                    );
+    // Rivers and paths cut into the terrain before anything is placed on it
+    let mut routes = Vec::new();
+    if let Some(terrain) = layers.iter_mut().find(|l| Some(l.name) == primary_terrain_name) {
+        let (carved, warnings) = carve_routes(&mut terrain.grid, (terrain.offset.0, terrain.offset.2), &scene.resolved);
+        for w in &warnings { eprintln!("warning: {w}"); }
+        routes = carved;
+        for route in &routes {
+            println!("  carved '{}': {} cells", route.name, route.cells.len());
+        }
+
+        // Carving can cut pieces loose
+        let found = scene.compiled.iter().zip(&scene.resolved.entities).find(|(e, _)| e.name == terrain.name);
+        if let (false, Some((ent, resolved))) = (routes.is_empty(), found) {
+            let pieces = floating_pieces(&terrain.grid, &ent.parts, resolved, scene.resolved.voxel_size);
+            if let Some(report) = FloatingReport::new(terrain.name, &pieces, resolved.grounding.policy, " after carving") {
+                if let Some(e) = report.violation() {
+                    eprintln!("[constraint] {e}");
+                    std::process::exit(1);
                 }
+                print_floating(&report);
             }
+            settle_floating(&mut terrain.grid, &pieces, resolved.grounding.policy);
+        }
+    }
+
+    for rel in scene.resolved.layout.iter().filter(|r| is_route(r)) {
+        let Some(route) = routes.iter().find(|r| r.name == rel.object.name) else { continue };
+        let Some(s) = layers.iter().position(|l| l.name == rel.subject.name) else {
+            eprintln!("warning: layout: '{}' is not drawn on its own", rel.subject.name);
+            continue;
+        };
+        let Some(t) = layers.iter().position(|l| Some(l.name) == primary_terrain_name) else { continue };
+        match span_route(rel, &layers[s].grid, route, &layers[t].grid, layers[t].offset, scene.resolved.voxel_size) {
+            Some((grid, offset)) => {
+                layers[s].grid = grid;
+                layers[s].offset = offset;
+                println!("  layout '{}' → {:?}", rel.subject.name, offset);
+            }
+            None => eprintln!("warning: layout: {} '{}' has no cells to cross", kind_label(route.kind), route.name),
+        }
+    }
+
+    // `place` statements, in order — later ones may rest on earlier ones
+    for placement in &scene.resolved.placements {
+        let Some(base) = placed_grids.get(placement.entity.as_str()) else { continue };
+        let grid = if placement.rotation.rem_euclid(4) != 0 {
+            base.rotated(Axis::Y, placement.rotation)
         } else {
-            eprintln!("warning: generators declared but no heightfield terrain found");
+            base.clone()
+        };
+
+        // Bottom-centre on `at`; `on` lifts it onto that surface's column
+        let (x, mut y, z) = placement.at;
+        if let Some(on) = &placement.on {
+            let surface = if on == "terrain" { primary_terrain_name } else { Some(on.as_str()) };
+            match surface.and_then(|name| surface_top(&layers, name, x, z)) {
+                Some(top) => y += top + 1,
+                None => eprintln!(
+                    "warning: '{}' placed on {on}, but there is no {on} surface at ({x}, {z})",
+                    placement.entity,
+                ),
+            }
+        }
+        let (gw, _, gd) = grid.dims();
+        let offset = (x - gw as i32 / 2, y, z - gd as i32 / 2);
+        println!("  placed '{}' at ({}, {}, {})", placement.entity, x, y, z);
+        layers.push(Layer { name: placement.entity.as_str(), grid, offset });
+    }
+
+    // Run generators over the layers they name
+    let mut placements = GeneratorOutput::default();
+    if !scene.resolved.generators.is_empty() {
+        let surfaces: Vec<Surface> = layers.iter()
+            .map(|l| Surface { name: l.name, grid: &l.grid, offset: l.offset })
+            .collect();
+        placements = run_generators(
+            &surfaces, primary_terrain_name, &scene.resolved, &scene.compiled, &routes,
+        );
+        warn_generators(&placements.reports);
+        println!("  placed {} instances", placements.instances.len());
+        if !placements.voxels.is_empty() {
+            println!("  grew {} vein voxels", placements.voxels.len());
+        }
+        if explain_generators {
+            print_generator_reports(&placements.reports);
+        }
+    }
+
+    // Veins replace voxels of their host layer
+    for v in &placements.voxels {
+        if let Some(layer) = layers.iter_mut().find(|l| l.name == v.host) {
+            let (ox, oy, oz) = layer.offset;
+            layer.grid.set(v.x - ox, v.y - oy, v.z - oz, v.atom_id);
+        }
+    }
+
+    // `print` decides what is drawn, and in what order: each printed entity's
+    // layers, then its generated instances
+    let (order, warnings) = scene.resolved.print_order();
+    for w in &warnings { eprintln!("warning: {w}"); }
+
+    // Build each distinct target/rotation/scale/tilt once, not once per
+    // instance.  Jittered instances all differ, so they are never shared.
+    type GridKey<'a> = (&'a str, i32, u64, (i32, i32, i32));
+    let mut target_grids: std::collections::HashMap<GridKey, moxi_lib::voxel::VoxelGrid> =
+        std::collections::HashMap::new();
+
+    for &name in &order {
+        for layer in layers.iter().filter(|l| l.name == name) {
+            all_voxels.extend(
+                grid_to_scene(&layer.grid, &scene.resolved.atoms, layer.offset).voxels
+            );
+        }
+
+        for placement in placements.instances.iter().filter(|i| i.target_name == name) {
+            let key = (
+                placement.target_name.as_str(), placement.rotation.rem_euclid(4),
+                placement.scale.to_bits(), placement.up,
+            );
+            let jittered;
+            let grid = if placement.jitter > 0.0 {
+                jittered = instance_grid(placement, &scene.resolved, &scene.compiled);
+                jittered.as_ref()
+            } else {
+                if let std::collections::hash_map::Entry::Vacant(slot) = target_grids.entry(key) {
+                    if let Some(g) = instance_grid(placement, &scene.resolved, &scene.compiled) {
+                        slot.insert(g);
+                    }
+                }
+                target_grids.get(&key)
+            };
+
+            if let Some(grid) = grid {
+                // Surface instances grow out of their face; embedded ones are
+                // centred on their host voxel
+                let world_off = instance_origin(placement, grid.dims());
+                all_voxels.extend(
+                    grid_to_scene(grid, &scene.resolved.atoms, world_off).voxels
+                );
+            }
         }
     }
 
@@ -251,18 +419,86 @@ fn build_world_scene(scene: &CompiledScene) -> VoxelScene {
     VoxelScene::new(all_voxels)
 }
 
+/// Highest filled world y in column (x, z) across the layers called `name`.
+fn surface_top(layers: &[Layer], name: &str, x: i32, z: i32) -> Option<i32> {
+    layers.iter().filter(|l| l.name == name).filter_map(|l| {
+        let (ox, oy, oz) = l.offset;
+        let (_, h, _) = l.grid.dims();
+        (0..h as i32).rev()
+            .find(|&y| l.grid.get(x - ox, y, z - oz) != 0)
+            .map(|y| y + oy)
+    }).max()
+}
+
+/// Generators that had nothing to scatter over, or placed fewer instances
+/// than their `count`.
+fn warn_generators(reports: &[GeneratorReport]) {
+    for r in reports {
+        for name in &r.missing {
+            if name == "terrain" {
+                eprintln!("warning: generator '{}' scatters over the terrain, but no heightfield terrain was found", r.name);
+            } else {
+                eprintln!("warning: generator '{}' scatters over '{name}', which is only drawn as generator instances", r.name);
+            }
+        }
+        // Nothing to scatter over says it already
+        let no_surface = !r.missing.is_empty() && r.candidates == 0;
+        if r.fell_short() && !no_surface {
+            eprintln!("warning: generator '{}' {r}", r.name);
+        }
+    }
+}
+
+/// One row per generator: where its candidate spots went.
+fn print_generator_reports(reports: &[GeneratorReport]) {
+    println!("  {:<20} {:>10} {:>7} {:>7} {:>8} {:>8} {:>8}",
+        "generator", "candidates", "where", "avoid", "overlap", "spacing", "placed");
+    for r in reports {
+        let placed = match r.requested {
+            Some(n) => format!("{}/{n}", r.placed),
+            None    => r.placed.to_string(),
+        };
+        println!("  {:<20} {:>10} {:>7} {:>7} {:>8} {:>8} {:>8}",
+            r.name, r.candidates, r.failed_where, r.rejected_avoid,
+            r.rejected_overlap, r.rejected_spacing, placed);
+    }
+}
 
 // ── Export ─────────────────────────────────────────────────────────────────
 
-fn run_export(scene: &CompiledScene, out_dir: &str) {
+fn run_export(scene: &CompiledScene, out_dir: &str, explain_generators: bool, show_overlaps: bool) {
     std::fs::create_dir_all(out_dir).ok();
-    let world = build_world_scene(scene);
+    let world = build_world_scene(scene, explain_generators, show_overlaps);
     let path  = format!("{out_dir}/world");
     if let Err(e) = export_to_obj(&world, &path) {
         eprintln!("export error: {e}");
     }
 }
 
+// ── Stats ──────────────────────────────────────────────────────────────────
+
+/// One table per entity: its parts, then the assembled whole and its
+/// inertia tensor.
+fn print_stats(scene: &CompiledScene) {
+    for (ent, resolved) in scene.compiled.iter().zip(&scene.resolved.entities) {
+        let stats = entity_stats(ent, resolved, &scene.resolved);
+        println!();
+        println!("  {:<20} {:>8} {:>10} {:>10}   centre of mass", ent.name, "voxels", "volume", "mass");
+        let row = |name: &str, m: &MassProps| {
+            let (x, y, z) = m.center_of_mass;
+            println!("  {name:<20} {:>8} {:>10.2} {:>10.2}   ({x:.2}, {y:.2}, {z:.2})", m.voxels, m.volume, m.mass);
+        };
+        for (name, m) in &stats.parts {
+            row(&format!("  {name}"), m);
+        }
+        row("total", &stats.total);
+        println!("  inertia about the centre of mass:");
+        for r in &stats.total.inertia {
+            println!("    [{:>12.2} {:>12.2} {:>12.2}]", r[0], r[1], r[2]);
+        }
+    }
+}
+
 // ── Check only ─────────────────────────────────────────────────────────────
 
 fn check_only(source: &str, path: &str) {
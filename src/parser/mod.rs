@@ -26,6 +26,11 @@ impl Parser {
         &self.peek().kind
     }
 
+    /// The kind of the token `n` places after the current one.
+    fn peek_nth_kind(&self, n: usize) -> &TokenKind {
+        &self.tokens[(self.cursor + n).min(self.tokens.len() - 1)].kind
+    }
+
     fn span(&self) -> Span {
         self.peek().span
     }
@@ -106,9 +111,17 @@ impl Parser {
             TokenKind::Material  => Ok(TopLevel::MaterialDecl(self.parse_material()?)),
             TokenKind::Entity    => Ok(TopLevel::EntityDecl(self.parse_entity()?)),
             TokenKind::Generator => Ok(TopLevel::GeneratorDecl(self.parse_generator()?)),
+            // `river` and `path` are only keywords here, so they stay usable
+            // as names and property keys everywhere else
+            TokenKind::Ident(k) if k == "river" => Ok(TopLevel::CarveDecl(self.parse_carve(CarveKind::River)?)),
+            TokenKind::Ident(k) if k == "path"  => Ok(TopLevel::CarveDecl(self.parse_carve(CarveKind::Path)?)),
             TokenKind::World     => Ok(TopLevel::WorldDecl(self.parse_world()?)),
+            TokenKind::Layout    => Ok(TopLevel::LayoutDecl(self.parse_layout()?)),
+            TokenKind::Place | TokenKind::Translate
+                                 => Ok(TopLevel::PlaceStmt(self.parse_place()?)),
             TokenKind::Print     => Ok(TopLevel::PrintStmt(self.parse_print()?)),
             TokenKind::Refine    => Ok(TopLevel::RefineStmt(self.parse_refine()?)),
+            TokenKind::Detail    => Ok(TopLevel::DetailDecl(self.parse_detail_decl()?)),
             other => Err(MoxiError::UnexpectedToken {
                 got: format!("{other:?}"),
                 expected: "top-level declaration".to_string(),
@@ -232,7 +245,7 @@ impl Parser {
                         Err(e) => { self.errors.push(e); self.advance(); }
                     }
                 }
-                TokenKind::Resolve => { resolve = Some(self.parse_resolve_opts()?); }
+                TokenKind::Resolve => { resolve = Some(self.parse_resolve_opts(resolve.take())?); }
                 TokenKind::Parts => {
                     self.advance();
                     self.expect_kind(&TokenKind::Eq, "'='")?;
@@ -251,11 +264,31 @@ impl Parser {
         let span = self.span();
         self.advance();
         let name = self.expect_ident()?;
-        self.expect_kind(&TokenKind::LBrace, "'{'")?;
         let mut shape     = None;
         let mut material  = None;
         let mut anchor    = None;
         let mut attach_to = None;
+
+        // `part RightArm mirror_of LeftArm axis=x`, body optional
+        let mut mirror_of = None;
+        if matches!(self.peek_kind(), TokenKind::Ident(ref k) if k == "mirror_of") {
+            self.advance();
+            let source = self.expect_ident()?;
+            let mut axis = None;
+            if matches!(self.peek_kind(), TokenKind::Ident(ref k) if k == "axis")
+                && matches!(self.peek_nth_kind(1), TokenKind::Eq)
+            {
+                self.advance();
+                self.advance();
+                axis = Some(self.expect_ident()?);
+            }
+            mirror_of = Some(MirrorSpec { source, axis });
+            if !matches!(self.peek_kind(), TokenKind::LBrace) {
+                return Ok(PartDecl { name, shape, mirror_of, material, anchor, attach_to, span });
+            }
+        }
+
+        self.expect_kind(&TokenKind::LBrace, "'{'")?;
         while !matches!(self.peek_kind(), TokenKind::RBrace | TokenKind::Eof) {
             match self.peek_kind().clone() {
                 TokenKind::Shape => {
@@ -269,26 +302,38 @@ impl Parser {
                     material = Some(self.expect_ident()?);
                 }
                 TokenKind::Ident(ref k) if k == "anchor" => {
+                    let anchor_span = self.span();
                     self.advance();
                     self.expect_kind(&TokenKind::Eq, "'='")?;
-                    anchor = Some(self.expect_ident()?);
+                    let value = self.parse_expr()?;
+                    anchor = Some(Prop { key: "anchor".to_string(), value, span: anchor_span });
                 }
+                // `attach_to = …` (`attach = …` also reads fine)
                 TokenKind::Attach => {
                     self.advance();
                     self.expect_kind(&TokenKind::Eq, "'='")?;
                     attach_to = Some(self.parse_attach_spec()?);
                 }
+                TokenKind::Ident(ref k) if k == "attach_to" => {
+                    self.advance();
+                    self.expect_kind(&TokenKind::Eq, "'='")?;
+                    attach_to = Some(self.parse_attach_spec()?);
+                }
                 TokenKind::Comma => { self.advance(); }
                 _ => { self.advance(); }
             }
         }
         self.expect_kind(&TokenKind::RBrace, "'}'")?;
-        Ok(PartDecl { name, shape, material, anchor, attach_to, span })
+        Ok(PartDecl { name, shape, mirror_of, material, anchor, attach_to, span })
     }
 
     fn parse_attach_spec(&mut self) -> Result<AttachSpec, MoxiError> {
+        let span = self.span();
         let fn_name = match self.peek_kind().clone() {
             TokenKind::Ident(s) => { self.advance(); s }
+            // `left_of` / `right_of` lex as relation keywords
+            TokenKind::LeftOf   => { self.advance(); "left_of".to_string() }
+            TokenKind::RightOf  => { self.advance(); "right_of".to_string() }
             other => return Err(MoxiError::UnexpectedToken {
                 got: format!("{other:?}"),
                 expected: "attach function".to_string(),
@@ -297,8 +342,13 @@ impl Parser {
         };
         self.expect_kind(&TokenKind::LParen, "'('")?;
         let target = self.expect_ident()?;
+        let mut args = Vec::new();
+        while matches!(self.peek_kind(), TokenKind::Comma) {
+            self.advance();
+            args.push(self.parse_expr()?);
+        }
         self.expect_kind(&TokenKind::RParen, "')'")?;
-        Ok(AttachSpec { anchor_fn: fn_name, target })
+        Ok(AttachSpec { anchor_fn: fn_name, target, args, span })
     }
 
     // ── shapes ────────────────────────────────────────────────────────────
@@ -361,6 +411,29 @@ impl Parser {
         Ok(args)
     }
 
+    /// Call arguments: positional values first, then `key = value` pairs.
+    /// `distance_to(Main)`, `noise(scale=0.1, seed=3)`
+    fn parse_call_args(&mut self) -> Result<(Vec<Expr>, Vec<NamedArg>), MoxiError> {
+        self.expect_kind(&TokenKind::LParen, "'('")?;
+        let mut positional = Vec::new();
+        let mut args       = Vec::new();
+        while !matches!(self.peek_kind(), TokenKind::RParen | TokenKind::Eof) {
+            let named = matches!(self.peek_kind(), TokenKind::Ident(_))
+                && self.tokens.get(self.cursor + 1).is_some_and(|t| t.kind == TokenKind::Eq);
+            if named {
+                let key = self.expect_ident()?.name;
+                self.advance(); // `=`
+                let value = self.parse_expr()?;
+                args.push(NamedArg { key, value });
+            } else {
+                positional.push(self.parse_expr()?);
+            }
+            if matches!(self.peek_kind(), TokenKind::Comma) { self.advance(); }
+        }
+        self.expect_kind(&TokenKind::RParen, "')'")?;
+        Ok((positional, args))
+    }
+
     // ── relations ─────────────────────────────────────────────────────────
 
     fn parse_relation_stmt(&mut self) -> Result<RelationStmt, MoxiError> {
@@ -368,7 +441,16 @@ impl Parser {
         let subject = self.expect_ident()?;
         let predicate = self.parse_relation_kind()?;
         let object = self.expect_ident()?;
+        let (qualifiers, args) = self.parse_relation_qualifiers()?;
+        Ok(RelationStmt { subject, predicate, object, qualifiers, args, span })
+    }
+
+    /// Qualifiers after a relation's object, optionally comma-separated:
+    /// bare words (`centered`, `flush_left`) and `key = value` pairs
+    /// (`gap = 2`, `offset = (0, 0, 1)`).  Stops at the next relation.
+    fn parse_relation_qualifiers(&mut self) -> Result<(Vec<Ident>, Vec<Prop>), MoxiError> {
         let mut qualifiers = Vec::new();
+        let mut args       = Vec::new();
         loop {
             match self.peek_kind().clone() {
                 TokenKind::Comma => { self.advance(); }
@@ -380,13 +462,20 @@ impl Parser {
                     {
                         break;
                     }
-                    qualifiers.push(Ident { name: q, span: self.span() });
+                    let q_span = self.span();
                     self.advance();
+                    if matches!(self.peek_kind(), TokenKind::Eq) {
+                        self.advance();
+                        let value = self.parse_expr()?;
+                        args.push(Prop { key: q, value, span: q_span });
+                    } else {
+                        qualifiers.push(Ident { name: q, span: q_span });
+                    }
                 }
                 _ => break,
             }
         }
-        Ok(RelationStmt { subject, predicate, object, qualifiers, span })
+        Ok((qualifiers, args))
     }
 
     fn is_relation_token_at(&self, idx: usize) -> bool {
@@ -396,6 +485,7 @@ impl Parser {
             TokenKind::RightOf| TokenKind::InFrontOf | TokenKind::Behind   |
             TokenKind::SymmetricAcross | TokenKind::AttachedTo             |
             TokenKind::Touch  | TokenKind::Surrounds)
+            || matches!(&self.tokens[idx].kind, TokenKind::Ident(k) if k == "on" || k == "across")
     }
 
     fn parse_relation_kind(&mut self) -> Result<RelationKind, MoxiError> {
@@ -429,8 +519,13 @@ impl Parser {
     fn parse_constraint_stmt(&mut self) -> Result<ConstraintStmt, MoxiError> {
         let span = self.span();
         self.advance(); // consume `constraint`
-        let subject = self.expect_ident()?;
-        let expr = if self.is_relation_token() {
+
+        // `Skull above Ribcage` and `no_overlap` start with a name and go on
+        // with a relation or nothing; everything else is a bound
+        let named = matches!(self.peek_kind(), TokenKind::Ident(_));
+        let next = self.peek_nth_kind(1).clone();
+        let expr = if named && is_relation_kind(&next) {
+            let subject = self.expect_ident()?;
             let predicate = self.parse_relation_kind()?;
             let object = self.expect_ident()?;
             let mut qualifiers = Vec::new();
@@ -438,38 +533,31 @@ impl Parser {
                 qualifiers.push(Ident { name: q, span: self.span() });
                 self.advance();
             }
-            ConstraintExpr::Relation(RelationStmt { subject, predicate, object, qualifiers, span })
+            ConstraintExpr::Relation(RelationStmt {
+                subject, predicate, object, qualifiers, args: Vec::new(), span,
+            })
+        } else if named && !matches!(next,
+            TokenKind::Dot | TokenKind::LParen | TokenKind::Plus | TokenKind::Minus |
+            TokenKind::Star | TokenKind::Slash) && cmp_op(&next).is_none()
+        {
+            ConstraintExpr::Rule(self.expect_ident()?)
         } else {
+            // `mass < 40`, `Skull.height < Spine.height / 4`, `distance(A, B) > 20`
+            let lhs = self.parse_expr_add()?;
             let op = self.parse_cmp_op()?;
-            let value = self.parse_expr()?;
-            ConstraintExpr::Bound { name: subject, op, value }
+            let rhs = self.parse_expr_add()?;
+            ConstraintExpr::Bound { lhs, op, rhs }
         };
         Ok(ConstraintStmt { expr, span })
     }
 
-    fn is_relation_token(&self) -> bool {
-        matches!(self.peek_kind(),
-            TokenKind::Inside | TokenKind::Outside | TokenKind::AdjacentTo |
-            TokenKind::Above  | TokenKind::Below   | TokenKind::LeftOf     |
-            TokenKind::RightOf| TokenKind::InFrontOf | TokenKind::Behind   |
-            TokenKind::SymmetricAcross | TokenKind::AttachedTo             |
-            TokenKind::Touch  | TokenKind::Surrounds)
-    }
-
     fn parse_cmp_op(&mut self) -> Result<CmpOp, MoxiError> {
-        let span = self.span();
-        let op = match self.peek_kind() {
-            TokenKind::Lt   => CmpOp::Lt,
-            TokenKind::Gt   => CmpOp::Gt,
-            TokenKind::LtEq => CmpOp::LtEq,
-            TokenKind::GtEq => CmpOp::GtEq,
-            TokenKind::EqEq => CmpOp::Eq,
-            TokenKind::Neq  => CmpOp::Neq,
-            other => return Err(MoxiError::UnexpectedToken {
-                got: format!("{other:?}"),
+        let Some(op) = cmp_op(self.peek_kind()) else {
+            return Err(MoxiError::UnexpectedToken {
+                got: format!("{:?}", self.peek_kind()),
                 expected: "comparison operator".to_string(),
-                span,
-            }),
+                span: self.span(),
+            });
         };
         self.advance();
         Ok(op)
@@ -483,7 +571,17 @@ impl Parser {
         let name = self.expect_ident()?;
         self.expect_kind(&TokenKind::LBrace, "'{'")?;
         self.expect_kind(&TokenKind::Scatter, "'scatter'")?;
-        let scatter_target = self.expect_ident()?;
+        let mut scatter_targets = Vec::new();
+        if matches!(self.peek_kind(), TokenKind::LBracket) {
+            self.advance();
+            while !matches!(self.peek_kind(), TokenKind::RBracket | TokenKind::Eof) {
+                scatter_targets.push(self.expect_ident()?);
+                if matches!(self.peek_kind(), TokenKind::Comma) { self.advance(); }
+            }
+            self.expect_kind(&TokenKind::RBracket, "']'")?;
+        } else {
+            scatter_targets.push(self.expect_ident()?);
+        }
         let mut props = Vec::new();
         while !matches!(self.peek_kind(), TokenKind::RBrace | TokenKind::Eof) {
             let prop_span = self.span();
@@ -492,6 +590,7 @@ impl Parser {
                 TokenKind::Over     => { self.advance(); "over".to_string() }
                 TokenKind::Where    => { self.advance(); "where".to_string() }
                 TokenKind::Avoid    => { self.advance(); "avoid".to_string() }
+                TokenKind::Inside   => { self.advance(); "inside".to_string() }
                 _ => { self.advance(); continue; }
             };
             self.expect_kind(&TokenKind::Eq, "'='")?;
@@ -499,7 +598,35 @@ impl Parser {
             props.push(Prop { key, value, span: prop_span });
         }
         self.expect_kind(&TokenKind::RBrace, "'}'")?;
-        Ok(GeneratorDecl { name, scatter_target, props, span })
+        Ok(GeneratorDecl { name, scatter_targets, props, span })
+    }
+
+    // ── river / path ──────────────────────────────────────────────────────
+
+    fn parse_carve(&mut self, kind: CarveKind) -> Result<CarveDecl, MoxiError> {
+        let span = self.span();
+        self.advance();
+        let name = self.expect_ident()?;
+        self.expect_kind(&TokenKind::LBrace, "'{'")?;
+        let mut props = Vec::new();
+        while !matches!(self.peek_kind(), TokenKind::RBrace | TokenKind::Eof) {
+            let prop_span = self.span();
+            let key = match self.peek_kind().clone() {
+                TokenKind::Ident(k) => { self.advance(); k }
+                TokenKind::Material => { self.advance(); "material".to_string() }
+                TokenKind::Comma    => { self.advance(); continue; }
+                other => return Err(MoxiError::UnexpectedToken {
+                    got: format!("{other:?}"),
+                    expected: "property name".to_string(),
+                    span: prop_span,
+                }),
+            };
+            self.expect_kind(&TokenKind::Eq, "'='")?;
+            let value = self.parse_expr()?;
+            props.push(Prop { key, value, span: prop_span });
+        }
+        self.expect_kind(&TokenKind::RBrace, "'}'")?;
+        Ok(CarveDecl { kind, name, props, span })
     }
 
     // ── world ─────────────────────────────────────────────────────────────
@@ -525,7 +652,7 @@ impl Parser {
                 TokenKind::Terrain  => { terrain = Some(self.parse_terrain_block()?); }
                 TokenKind::Biome    => { biomes.push(self.parse_biome_block()?); }
                 TokenKind::Water    => { water = Some(self.parse_water_block()?); }
-                TokenKind::Resolve  => { resolve = Some(self.parse_resolve_opts()?); }
+                TokenKind::Resolve  => { resolve = Some(self.parse_resolve_opts(resolve.take())?); }
                 _ => { self.advance(); }
             }
         }
@@ -612,22 +739,95 @@ impl Parser {
         Ok(WaterBlock { level, material, depth_material })
     }
 
-    fn parse_resolve_opts(&mut self) -> Result<ResolveOpts, MoxiError> {
+    /// `resolve voxel_size = 1.0` (the key is optional) or `resolve key = value`,
+    /// added to the settings of earlier `resolve` lines.
+    fn parse_resolve_opts(&mut self, earlier: Option<ResolveOpts>) -> Result<ResolveOpts, MoxiError> {
+        let mut opts = earlier.unwrap_or_default();
         self.advance(); // `resolve`
-        // consume `voxel_size` identifier
-        match self.peek_kind().clone() {
-            TokenKind::Ident(ref k) if k == "voxel_size" => { self.advance(); }
-            _ => {}
-        }
+        let key = match self.peek_kind().clone() {
+            TokenKind::Ident(k) if k != "voxel_size" => {
+                let span = self.span();
+                self.advance();
+                Some((k, span))
+            }
+            TokenKind::Ident(_) => { self.advance(); None }
+            _ => None,
+        };
         self.expect_kind(&TokenKind::Eq, "'='")?;
-        let voxel_size = match self.peek_kind().clone() {
+        if let Some((key, span)) = key {
+            let value = self.parse_expr()?;
+            opts.props.push(Prop { key, value, span });
+            return Ok(opts);
+        }
+        opts.voxel_size = Some(match self.peek_kind().clone() {
             TokenKind::Float(f) => { self.advance(); f }
             TokenKind::Int(n)   => { self.advance(); n as f64 }
             other => return Err(MoxiError::UnexpectedToken {
                 got: format!("{other:?}"), expected: "voxel size".to_string(), span: self.span(),
             }),
-        };
-        Ok(ResolveOpts { voxel_size })
+        });
+        Ok(opts)
+    }
+
+    // ── layout ────────────────────────────────────────────────────────────
+
+    fn parse_layout(&mut self) -> Result<LayoutDecl, MoxiError> {
+        let span = self.span();
+        self.advance();
+        self.expect_kind(&TokenKind::LBrace, "'{'")?;
+        let mut relations = Vec::new();
+        while !matches!(self.peek_kind(), TokenKind::RBrace | TokenKind::Eof) {
+            if matches!(self.peek_kind(), TokenKind::Comma) {
+                self.advance();
+                continue;
+            }
+            let rel_span = self.span();
+            let subject = self.expect_ident()?;
+            let predicate = match self.peek_kind() {
+                TokenKind::Ident(k) if k == "on"     => { self.advance(); RelationKind::On }
+                TokenKind::Ident(k) if k == "across" => { self.advance(); RelationKind::Across }
+                _ => self.parse_relation_kind()?,
+            };
+            let object = if matches!(self.peek_kind(), TokenKind::Terrain) {
+                let object = Ident { name: "terrain".to_string(), span: self.span() };
+                self.advance();
+                object
+            } else {
+                self.expect_ident()?
+            };
+            let (qualifiers, args) = self.parse_relation_qualifiers()?;
+            relations.push(RelationStmt { subject, predicate, object, qualifiers, args, span: rel_span });
+        }
+        self.expect_kind(&TokenKind::RBrace, "'}'")?;
+        Ok(LayoutDecl { relations, span })
+    }
+
+    // ── place ─────────────────────────────────────────────────────────────
+
+    fn parse_place(&mut self) -> Result<PlaceStmt, MoxiError> {
+        let span = self.span();
+        self.advance();
+        let target = self.expect_ident()?;
+        if matches!(self.peek_kind(), TokenKind::Ident(ref k) if k == "at") {
+            self.advance();
+        }
+        let at = self.parse_expr()?;
+
+        // Trailing `key = value` pairs; statements have no terminator, so stop
+        // at anything that is not followed by '='
+        let mut props = Vec::new();
+        loop {
+            let prop_span = self.span();
+            let key = match self.peek_kind() {
+                TokenKind::Ident(k) if matches!(self.peek_nth_kind(1), TokenKind::Eq) => k.clone(),
+                _ => break,
+            };
+            self.advance();
+            self.advance();
+            let value = self.parse_expr()?;
+            props.push(Prop { key, value, span: prop_span });
+        }
+        Ok(PlaceStmt { target, at, props, span })
     }
 
     // ── print / refine ────────────────────────────────────────────────────
@@ -644,6 +844,15 @@ impl Parser {
         Ok(PrintStmt { target, detail, span })
     }
 
+    fn parse_detail_decl(&mut self) -> Result<DetailDecl, MoxiError> {
+        let span = self.span();
+        self.advance();
+        self.expect_kind(&TokenKind::LBrace, "'{'")?;
+        let props = self.parse_prop_list()?;
+        self.expect_kind(&TokenKind::RBrace, "'}'")?;
+        Ok(DetailDecl { props, span })
+    }
+
     fn parse_refine(&mut self) -> Result<RefineStmt, MoxiError> {
         let span = self.span();
         self.advance();
@@ -698,7 +907,17 @@ impl Parser {
 
     // ── expressions ───────────────────────────────────────────────────────
 
-    fn parse_expr(&mut self) -> Result<Expr, MoxiError> { self.parse_expr_or() }
+    fn parse_expr(&mut self) -> Result<Expr, MoxiError> {
+        let lhs = self.parse_expr_or()?;
+        // `lo..hi` — a range
+        if matches!(self.peek_kind(), TokenKind::Dot) && matches!(self.peek_nth_kind(1), TokenKind::Dot) {
+            self.advance();
+            self.advance();
+            let rhs = self.parse_expr_or()?;
+            return Ok(Expr::Range { lo: Box::new(lhs), hi: Box::new(rhs) });
+        }
+        Ok(lhs)
+    }
 
     fn parse_expr_or(&mut self) -> Result<Expr, MoxiError> {
         let mut lhs = self.parse_expr_and()?;
@@ -737,7 +956,7 @@ impl Parser {
     }
 
     fn parse_expr_add(&mut self) -> Result<Expr, MoxiError> {
-        let mut lhs = self.parse_expr_unary()?;
+        let mut lhs = self.parse_expr_mul()?;
         loop {
             let op = match self.peek_kind() {
                 TokenKind::Plus  => BinOp::Add,
@@ -745,6 +964,21 @@ impl Parser {
                 _ => break,
             };
             self.advance();
+            let rhs = self.parse_expr_mul()?;
+            lhs = Expr::BinOp { op, lhs: Box::new(lhs), rhs: Box::new(rhs) };
+        }
+        Ok(lhs)
+    }
+
+    fn parse_expr_mul(&mut self) -> Result<Expr, MoxiError> {
+        let mut lhs = self.parse_expr_unary()?;
+        loop {
+            let op = match self.peek_kind() {
+                TokenKind::Star  => BinOp::Mul,
+                TokenKind::Slash => BinOp::Div,
+                _ => break,
+            };
+            self.advance();
             let rhs = self.parse_expr_unary()?;
             lhs = Expr::BinOp { op, lhs: Box::new(lhs), rhs: Box::new(rhs) };
         }
@@ -778,18 +1012,39 @@ impl Parser {
             TokenKind::LParen => {
                 self.advance();
                 let inner = self.parse_expr()?;
+                if !matches!(self.peek_kind(), TokenKind::Comma) {
+                    self.expect_kind(&TokenKind::RParen, "')'")?;
+                    return Ok(inner);
+                }
+                // `(a, b, …)` — a tuple
+                let mut items = vec![inner];
+                while matches!(self.peek_kind(), TokenKind::Comma) {
+                    self.advance();
+                    if matches!(self.peek_kind(), TokenKind::RParen) { break; }
+                    items.push(self.parse_expr()?);
+                }
                 self.expect_kind(&TokenKind::RParen, "')'")?;
-                Ok(inner)
+                Ok(Expr::Tuple(items))
             }
             TokenKind::Ident(name) => {
                 self.advance();
                 if matches!(self.peek_kind(), TokenKind::LParen) {
-                    let args = self.parse_named_args()?;
-                    Ok(Expr::Call { name, args })
+                    let (positional, args) = self.parse_call_args()?;
+                    Ok(Expr::Call { name, positional, args })
+                } else if matches!(self.peek_kind(), TokenKind::Dot)
+                    && matches!(self.peek_nth_kind(1), TokenKind::Ident(_))
+                {
+                    // `Skull.height`
+                    self.advance();
+                    let property = self.expect_ident()?;
+                    Ok(Expr::Property { owner: Ident { name, span }, name: property })
                 } else {
                     Ok(Expr::Ident(Ident { name, span }))
                 }
             }
+            // `over = terrain` / `over = world` — keywords used as values
+            TokenKind::Terrain => { self.advance(); Ok(Expr::Ident(Ident { name: "terrain".into(), span })) }
+            TokenKind::World   => { self.advance(); Ok(Expr::Ident(Ident { name: "world".into(), span })) }
             other => Err(MoxiError::UnexpectedToken {
                 got: format!("{other:?}"),
                 expected: "expression".to_string(),
@@ -797,4 +1052,114 @@ impl Parser {
             }),
         }
     }
-}
\ No newline at end of file
+}
+
+fn is_relation_kind(kind: &TokenKind) -> bool {
+    matches!(kind,
+        TokenKind::Inside | TokenKind::Outside | TokenKind::AdjacentTo |
+        TokenKind::Above  | TokenKind::Below   | TokenKind::LeftOf     |
+        TokenKind::RightOf| TokenKind::InFrontOf | TokenKind::Behind   |
+        TokenKind::SymmetricAcross | TokenKind::AttachedTo             |
+        TokenKind::Touch  | TokenKind::Surrounds)
+}
+
+fn cmp_op(kind: &TokenKind) -> Option<CmpOp> {
+    Some(match kind {
+        TokenKind::Lt   => CmpOp::Lt,
+        TokenKind::Gt   => CmpOp::Gt,
+        TokenKind::LtEq => CmpOp::LtEq,
+        TokenKind::GtEq => CmpOp::GtEq,
+        TokenKind::EqEq => CmpOp::Eq,
+        TokenKind::Neq  => CmpOp::Neq,
+        _               => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn parse(src: &str) -> Vec<TopLevel> {
+        let (tokens, lex_errors) = Lexer::new(src).tokenize();
+        let (doc, errors) = Parser::new(tokens).parse();
+        assert!(lex_errors.is_empty() && errors.is_empty(), "{lex_errors:?} {errors:?}");
+        doc.items
+    }
+
+    fn prop<'a>(props: &'a [Prop], key: &str) -> &'a Expr {
+        &props.iter().find(|p| p.key == key).unwrap_or_else(|| panic!("no `{key}`")).value
+    }
+
+    #[test]
+    fn tuples_and_parenthesised_expressions() {
+        let items = parse("river Main { from = (3, -2), to = sea, width = (1 + 2) * 2 }");
+        let TopLevel::CarveDecl(river) = &items[0] else { panic!("{items:?}") };
+        assert_eq!((river.kind, river.name.name.as_str()), (CarveKind::River, "Main"));
+
+        let Expr::Tuple(from) = prop(&river.props, "from") else { panic!("{:?}", river.props) };
+        assert!(matches!(from[..], [Expr::Int(3), Expr::Int(-2)]));
+        assert!(matches!(prop(&river.props, "width"), Expr::BinOp { op: BinOp::Mul, .. }));
+    }
+
+    #[test]
+    fn river_and_path_still_work_as_names() {
+        let items = parse("
+            path Trail { from = (0, 0), to = (5, 5) }
+            generator G { scatter path river = 3 }
+        ");
+        assert!(matches!(&items[0], TopLevel::CarveDecl(d) if d.kind == CarveKind::Path));
+        let TopLevel::GeneratorDecl(g) = &items[1] else { panic!("{items:?}") };
+        assert_eq!(g.scatter_targets[0].name, "path");
+        assert!(matches!(prop(&g.props, "river"), Expr::Int(3)));
+    }
+
+    #[test]
+    fn ranges_of_ints_and_floats() {
+        let items = parse("generator G { scatter T scale = 0.8..1.2 radius = 2..5 }");
+        let TopLevel::GeneratorDecl(g) = &items[0] else { panic!("{items:?}") };
+        let Expr::Range { lo, hi } = prop(&g.props, "scale") else { panic!("{:?}", g.props) };
+        assert!(matches!((&**lo, &**hi), (Expr::Float(l), Expr::Float(h)) if *l == 0.8 && *h == 1.2));
+        let Expr::Range { lo, hi } = prop(&g.props, "radius") else { panic!("{:?}", g.props) };
+        assert!(matches!((&**lo, &**hi), (Expr::Int(2), Expr::Int(5))));
+    }
+
+    #[test]
+    fn place_and_translate_statements_end_at_the_next_statement() {
+        let items = parse("
+            place Lighthouse at (30, 0, -12) rotate = 90 on = terrain
+            translate Hut (1, 2, 3)
+            print Lighthouse
+        ");
+        let [TopLevel::PlaceStmt(light), TopLevel::PlaceStmt(hut), TopLevel::PrintStmt(_)] = &items[..] else {
+            panic!("{items:?}")
+        };
+        assert_eq!(light.target.name, "Lighthouse");
+        assert!(matches!(&light.at, Expr::Tuple(xyz) if xyz.len() == 3));
+        assert!(matches!(prop(&light.props, "rotate"), Expr::Int(90)));
+        assert!(matches!(prop(&light.props, "on"), Expr::Ident(i) if i.name == "terrain"));
+        assert!(hut.target.name == "Hut" && hut.props.is_empty());
+    }
+
+    #[test]
+    fn layout_relations_take_qualifiers() {
+        let items = parse("layout { Castle on Hill, Village left_of Castle gap=10, Bridge across River }");
+        let TopLevel::LayoutDecl(layout) = &items[0] else { panic!("{items:?}") };
+        let predicates: Vec<&RelationKind> = layout.relations.iter().map(|r| &r.predicate).collect();
+        assert_eq!(predicates, [&RelationKind::On, &RelationKind::LeftOf, &RelationKind::Across]);
+
+        let village = &layout.relations[1];
+        assert_eq!((village.subject.name.as_str(), village.object.name.as_str()), ("Village", "Castle"));
+        assert!(matches!(prop(&village.args, "gap"), Expr::Int(10)));
+    }
+
+    #[test]
+    fn bounds_read_part_properties() {
+        let items = parse("entity Body { constraint Skull.height < Spine.height / 4 }");
+        let TopLevel::EntityDecl(body) = &items[0] else { panic!("{items:?}") };
+        let ConstraintExpr::Bound { lhs, op, rhs } = &body.constraints[0].expr else { panic!("{body:?}") };
+        assert!(matches!(lhs, Expr::Property { owner, name } if owner.name == "Skull" && name.name == "height"));
+        assert_eq!(*op, CmpOp::Lt);
+        assert!(matches!(rhs, Expr::BinOp { op: BinOp::Div, lhs, .. } if matches!(&**lhs, Expr::Property { .. })));
+    }
+}
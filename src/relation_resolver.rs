@@ -6,9 +6,13 @@
 // This runs AFTER geometry compilation and BEFORE OBJ export.
 // The geometry backend stamps shapes at origin. We move them here.
 
-use std::collections::HashMap;
-use crate::ast::{RelationKind, RelationStmt};
-use crate::geometry::CompiledPart;
+use std::collections::{BTreeSet, HashMap};
+use crate::ast::{Expr, RelationKind, RelationStmt};
+use crate::carve::CarvedRoute;
+use crate::error::MoxiError;
+use crate::geom::Axis;
+use crate::geometry::{grid_center, merge_parts, CompiledPart};
+use crate::voxel::VoxelGrid;
 
 // ── Public types ───────────────────────────────────────────────────────────
 
@@ -27,14 +31,23 @@ pub type OffsetMap = HashMap<String, PartOffset>;
 
 /// Compute offsets for all parts in one entity given its relation statements.
 ///
-/// `parts`     — compiled parts, each with a bounding box at origin
-/// `relations` — the `RelationStmt` list from the resolved AST
+/// `parts`      — compiled parts, each with a bounding box at origin
+/// `relations`  — the `RelationStmt` list from the resolved AST
+/// `voxel_size` — world voxel size, to turn qualifier lengths into voxels
 ///
 /// Returns a map from part name → (dx, dy, dz) offset.
 /// Parts not mentioned in any relation get offset (0,0,0).
+///
+/// Parts are placed in dependency order (see `solve_order`), so the result
+/// does not depend on the order relations are written in.  Each axis of a
+/// part comes from the relation that fixes it (`fixed_axes`); an axis no
+/// relation fixes is aligned with the object of the first relation by name.
+/// `symmetric_across` copies get an entry too, under their part name or
+/// the name `placed_part` makes up for them.
 pub fn resolve_offsets(
-    parts:     &[CompiledPart],
-    relations: &[RelationStmt],
+    parts:      &[CompiledPart],
+    relations:  &[RelationStmt],
+    voxel_size: f64,
 ) -> OffsetMap {
     // Build name → bounding box map for fast lookup
     let bbox_map: HashMap<&str, BBox> = parts
@@ -48,45 +61,169 @@ pub fn resolve_offsets(
         .map(|p| (p.name.clone(), PartOffset::default()))
         .collect();
 
-    // Process each relation in order.
-    // We do multiple passes to propagate chains:
-    //   Skull above Spine, Spine above Pelvis
-    // needs Spine's offset to be settled before Skull's is computed.
-    for _ in 0..4 {
-        for rel in relations {
-            apply_relation(rel, &bbox_map, &mut offsets);
+    let mirrors = mirror_parts(parts);
+    for (name, group) in solve_order(relations, |r| placed_part(r, &mirrors)) {
+        // A mirrored copy lands opposite its subject
+        if let [rel] = group.as_slice() {
+            if rel.predicate == RelationKind::SymmetricAcross {
+                let subj = rel.subject.name.as_str();
+                let (Some(sb), Some(ob)) = (bbox_map.get(subj), bbox_map.get(rel.object.name.as_str())) else { continue };
+                let current = offsets.get(subj).copied().unwrap_or_default();
+                let obj_off = offsets.get(&rel.object.name).copied().unwrap_or_default();
+                offsets.insert(name, relation_offset(rel, sb, ob, obj_off, current, voxel_size));
+                continue;
+            }
         }
+
+        let Some(sb) = bbox_map.get(name.as_str()) else { continue };
+        let placed: Vec<(&RelationStmt, PartOffset)> = group.iter()
+            .filter_map(|&rel| {
+                let ob = bbox_map.get(rel.object.name.as_str())?;
+                let obj_off = offsets.get(&rel.object.name).copied().unwrap_or_default();
+                let grid_of = |n: &str| parts.iter().find(|p| p.name == n).map(|p| &p.grid);
+                if let Some(attach) = rel.args.iter().find(|a| a.key == "attach") {
+                    let off = attach_offset(rel, &attach.value, sb, ob, obj_off, grid_of(&rel.object.name)?, voxel_size);
+                    return Some((rel, off));
+                }
+                let mut off = relation_offset(rel, sb, ob, obj_off, PartOffset::default(), voxel_size);
+
+                // Contact: slide along the relation's axis onto the voxels
+                let q = Qualifiers::of(rel, voxel_size);
+                if let (true, Some((axis, sign))) = (q.contact, push_direction(&rel.predicate)) {
+                    let (sg, og) = (grid_of(&name)?, grid_of(&rel.object.name)?);
+                    let shift = |grid: &VoxelGrid, o: PartOffset| {
+                        let (cx, cy, cz) = grid_center(grid);
+                        (o.dx - cx, o.dy - cy, o.dz - cz)
+                    };
+                    if let Some(at) = contact_position(sg, shift(sg, off), og, shift(og, obj_off), axis, sign) {
+                        let centre = [grid_center(sg).0, grid_center(sg).1, grid_center(sg).2][axis];
+                        let extra  = sign * q.gap + [q.offset.0, q.offset.1, q.offset.2][axis];
+                        match axis {
+                            0 => off.dx = at + centre + extra,
+                            1 => off.dy = at + centre + extra,
+                            _ => off.dz = at + centre + extra,
+                        }
+                    }
+                }
+                Some((rel, off))
+            })
+            .collect();
+
+        let axis = |i: usize, pick: fn(&PartOffset) -> i32| -> i32 {
+            placed.iter()
+                .find(|(rel, _)| fixed_axes(&rel.predicate)[i])
+                .or(placed.first())
+                .map(|(_, off)| pick(off))
+                .unwrap_or(0)
+        };
+        let off = PartOffset { dx: axis(0, |o| o.dx), dy: axis(1, |o| o.dy), dz: axis(2, |o| o.dz) };
+        offsets.insert(name, off);
     }
 
     offsets
 }
 
-// ── Relation application ───────────────────────────────────────────────────
+/// The `mirror_of` parts of an entity as (name, source, axis).
+fn mirror_parts(parts: &[CompiledPart]) -> Vec<(String, String, Axis)> {
+    parts.iter()
+        .filter_map(|p| p.mirror_of.as_ref().map(|(source, axis)| (p.name.clone(), source.clone(), *axis)))
+        .collect()
+}
 
-fn apply_relation(
-    rel:      &RelationStmt,
-    bbox_map: &HashMap<&str, BBox>,
-    offsets:  &mut OffsetMap,
-) {
-    let subj = rel.subject.name.as_str();
-    let obj  = rel.object.name.as_str();
+/// Resolve offsets and merge the parts into one grid — the entity as it
+/// appears in the world.
+/// `symmetric_across` copies with no `mirror_of` part are added here.
+pub fn assemble_entity(parts: &[CompiledPart], relations: &[RelationStmt], voxel_size: f64) -> VoxelGrid {
+    let offsets = resolve_offsets(parts, relations, voxel_size);
+    let offsets_vec: Vec<_> = offsets.iter()
+        .map(|(n, o)| (n.clone(), (o.dx, o.dy, o.dz)))
+        .collect();
 
-    let Some(sb) = bbox_map.get(subj) else { return };
-    let Some(ob) = bbox_map.get(obj)  else { return };
+    let copies = mirror_copies(parts, relations);
+    if copies.is_empty() {
+        return merge_parts(parts, &offsets_vec);
+    }
+    let all: Vec<CompiledPart> = parts.iter().cloned().chain(copies).collect();
+    merge_parts(&all, &offsets_vec)
+}
 
-    // Current offset of the object (the anchor part)
-    let obj_off = offsets.get(obj).copied().unwrap_or_default();
+/// The unnamed copies `symmetric_across` draws for parts with no
+/// `mirror_of` counterpart, named as `placed_part` names them.
+pub fn mirror_copies(parts: &[CompiledPart], relations: &[RelationStmt]) -> Vec<CompiledPart> {
+    let mirrors = mirror_parts(parts);
+    relations.iter()
+        .filter(|r| r.predicate == RelationKind::SymmetricAcross)
+        .filter_map(|rel| {
+            let name = placed_part(rel, &mirrors);
+            if parts.iter().any(|p| p.name == name) { return None; }
+            let source = parts.iter().find(|p| p.name == rel.subject.name)?;
+            Some(CompiledPart {
+                name,
+                grid:       source.grid.mirrored(mirror_axis(rel)),
+                atom_id:    source.atom_id,
+                voxel_size: source.voxel_size,
+                mirror_of:  Some((source.name.clone(), mirror_axis(rel))),
+            })
+        })
+        .collect()
+}
 
+// ── Relation application ───────────────────────────────────────────────────
+
+/// Where the subject goes for one relation, given the object's offset,
+/// qualifiers included.  `current` is where the subject already is (only
+/// `symmetric_across` reads it).
+fn relation_offset(
+    rel:        &RelationStmt,
+    sb:         &BBox,
+    ob:         &BBox,
+    obj_off:    PartOffset,
+    current:    PartOffset,
+    voxel_size: f64,
+) -> PartOffset {
+    let q = Qualifiers::of(rel, voxel_size);
+    let mut off = [0; 3];
+    let base = unqualified_offset(rel, sb, ob, obj_off, current);
+    off[0] = match q.x {
+        Align::Centre => base.dx,
+        Align::Min    => obj_off.dx + ob.min_x - sb.min_x,
+        Align::Max    => obj_off.dx + ob.max_x - sb.max_x,
+    };
+    off[1] = base.dy;
+    off[2] = match q.z {
+        Align::Centre => base.dz,
+        Align::Min    => obj_off.dz + ob.min_z - sb.min_z,
+        Align::Max    => obj_off.dz + ob.max_z - sb.max_z,
+    };
+    if let Some((axis, sign)) = push_direction(&rel.predicate) {
+        off[axis] += sign * q.gap;
+    }
+    PartOffset {
+        dx: off[0] + q.offset.0,
+        dy: off[1] + q.offset.1,
+        dz: off[2] + q.offset.2,
+    }
+}
+
+fn unqualified_offset(
+    rel:     &RelationStmt,
+    sb:      &BBox,
+    ob:      &BBox,
+    obj_off: PartOffset,
+    current: PartOffset,
+) -> PartOffset {
     // Compute where the subject should go relative to the object
-    let new_off = match rel.predicate {
+    match rel.predicate {
 
         // ── Vertical ──────────────────────────────────────────────────────
 
         // Subject sits directly above object.
-        // Subject's base (min_y + dy) = object's top (max_y + obj_off.dy)
-        RelationKind::Above => PartOffset {
+        // Subject's base (min_y + dy) = object's top (max_y + obj_off.dy) + 1
+        // `on` and `across` are world-layout relations; between parts they
+        // mean the same.
+        RelationKind::Above | RelationKind::On | RelationKind::Across => PartOffset {
             dx: obj_off.dx,
-            dy: obj_off.dy + ob.max_y + 1,
+            dy: obj_off.dy + ob.max_y + 1 - sb.min_y,
             dz: obj_off.dz,
         },
 
@@ -109,14 +246,16 @@ fn apply_relation(
 
         // ── Lateral ───────────────────────────────────────────────────────
 
+        // Subject's facing side sits one voxel past the object's, like
+        // `above`: subject's max_x + dx = object's min_x + obj_off.dx - 1
         RelationKind::LeftOf => PartOffset {
-            dx: obj_off.dx - sb.width(),
+            dx: obj_off.dx + ob.min_x - sb.max_x - 1,
             dy: obj_off.dy + ob.center_y() - sb.center_y(),
             dz: obj_off.dz + ob.center_z() - sb.center_z(),
         },
 
         RelationKind::RightOf => PartOffset {
-            dx: obj_off.dx + ob.width(),
+            dx: obj_off.dx + ob.max_x + 1 - sb.min_x,
             dy: obj_off.dy + ob.center_y() - sb.center_y(),
             dz: obj_off.dz + ob.center_z() - sb.center_z(),
         },
@@ -124,13 +263,13 @@ fn apply_relation(
         RelationKind::InFrontOf => PartOffset {
             dx: obj_off.dx + ob.center_x() - sb.center_x(),
             dy: obj_off.dy + ob.center_y() - sb.center_y(),
-            dz: obj_off.dz - sb.depth(),
+            dz: obj_off.dz + ob.min_z - sb.max_z - 1,
         },
 
         RelationKind::Behind => PartOffset {
             dx: obj_off.dx + ob.center_x() - sb.center_x(),
             dy: obj_off.dy + ob.center_y() - sb.center_y(),
-            dz: obj_off.dz + ob.depth(),
+            dz: obj_off.dz + ob.max_z + 1 - sb.min_z,
         },
 
         // ── Contact ───────────────────────────────────────────────────────
@@ -144,14 +283,25 @@ fn apply_relation(
 
         // ── Symmetric ─────────────────────────────────────────────────────
 
-        // Mirror the subject across the midline of the object.
-        // For now: mirror on X axis (bilateral symmetry).
+        // The mirrored copy of the subject (at `current`) lands opposite it,
+        // reflected through the plane across the object's centre.
         RelationKind::SymmetricAcross => {
-            let current = offsets.get(subj).copied().unwrap_or_default();
-            PartOffset {
-                dx: obj_off.dx + ob.center_x() - (current.dx - ob.center_x()),
-                dy: current.dy,
-                dz: current.dz,
+            let reflect = |d: i32, o: i32, (omin, omax): (i32, i32), (smin, smax): (i32, i32)| {
+                2 * o + omin + omax - smin - smax - d
+            };
+            match mirror_axis(rel) {
+                Axis::X => PartOffset {
+                    dx: reflect(current.dx, obj_off.dx, (ob.min_x, ob.max_x), (sb.min_x, sb.max_x)),
+                    ..current
+                },
+                Axis::Y => PartOffset {
+                    dy: reflect(current.dy, obj_off.dy, (ob.min_y, ob.max_y), (sb.min_y, sb.max_y)),
+                    ..current
+                },
+                Axis::Z => PartOffset {
+                    dz: reflect(current.dz, obj_off.dz, (ob.min_z, ob.max_z), (sb.min_z, sb.max_z)),
+                    ..current
+                },
             }
         }
 
@@ -159,7 +309,7 @@ fn apply_relation(
 
         // Place subject just outside the object's bounding box on X+
         RelationKind::Outside => PartOffset {
-            dx: obj_off.dx + ob.width(),
+            dx: obj_off.dx + ob.max_x + 1 - sb.min_x,
             dy: obj_off.dy + ob.center_y() - sb.center_y(),
             dz: obj_off.dz + ob.center_z() - sb.center_z(),
         },
@@ -172,9 +322,561 @@ fn apply_relation(
                 dz: obj_off.dz + ob.center_z() - sb.center_z(),
             }
         }
+    }
+}
+
+// ── Anchors ────────────────────────────────────────────────────────────────
+//
+// `attach_to = top_of(Trunk)` lands a point of the part — its `anchor`, or
+// the side facing the target — on a point of the target.  The resolver
+// turns it into the relation it stands for (`top_of` is `above`), with the
+// anchor function and anchor riding along as `attach` / `anchor` args, so it
+// is ordered and conflict-checked with the rest.
+
+/// The relation an `attach_to` anchor function stands for; `None` if there
+/// is no such function.
+pub fn attach_relation(anchor_fn: &str) -> Option<RelationKind> {
+    Some(match anchor_fn {
+        "top_of" | "surface_at" => RelationKind::Above,
+        "bottom_of"             => RelationKind::Below,
+        "left_of"               => RelationKind::LeftOf,
+        "right_of"              => RelationKind::RightOf,
+        "front_of"              => RelationKind::InFrontOf,
+        "back_of"               => RelationKind::Behind,
+        "center_of"             => RelationKind::Inside,
+        _                       => return None,
+    })
+}
+
+/// Offset that lands the subject's anchor point on the point `attach`
+/// (`top_of(T)`, `surface_at(T, u, v)`, …) names on the object.  Points are
+/// in the parts' centred bounding-box space; target points sit just outside
+/// the named face, so a part attached by its facing side touches without
+/// overlapping.
+fn attach_offset(
+    rel:        &RelationStmt,
+    attach:     &Expr,
+    sb:         &BBox,
+    ob:         &BBox,
+    obj_off:    PartOffset,
+    object:     &VoxelGrid,
+    voxel_size: f64,
+) -> PartOffset {
+    let Expr::Call { name, positional, .. } = attach else { return obj_off };
+    let mid = |lo: i32, hi: i32| (lo + hi) / 2;
+    let (ox, oy, oz) = (mid(ob.min_x, ob.max_x), mid(ob.min_y, ob.max_y), mid(ob.min_z, ob.max_z));
+    let (sx, sy, sz) = (mid(sb.min_x, sb.max_x), mid(sb.min_y, sb.max_y), mid(sb.min_z, sb.max_z));
+
+    // Point on the target, and the subject's matching side
+    let (target, side) = match name.as_str() {
+        "bottom_of" => ((ox, ob.min_y - 1, oz), (sx, sb.max_y, sz)),
+        "left_of"   => ((ob.min_x - 1, oy, oz), (sb.max_x, sy, sz)),
+        "right_of"  => ((ob.max_x + 1, oy, oz), (sb.min_x, sy, sz)),
+        "front_of"  => ((ox, oy, ob.min_z - 1), (sx, sy, sb.max_z)),
+        "back_of"   => ((ox, oy, ob.max_z + 1), (sx, sy, sb.min_z)),
+        "center_of" => ((ox, oy, oz), (sx, sy, sz)),
+        "surface_at" => {
+            // (u, v) across the target's footprint, on its highest voxel there
+            let frac = |e: Option<&Expr>| match e {
+                Some(Expr::Int(n))   => *n as f64,
+                Some(Expr::Float(f)) => *f,
+                _                    => 0.5,
+            };
+            let tx = ob.min_x + (frac(positional.first()) * (ob.max_x - ob.min_x) as f64).round() as i32;
+            let tz = ob.min_z + (frac(positional.get(1)) * (ob.max_z - ob.min_z) as f64).round() as i32;
+            let (gx, gy, gz) = grid_center(object);
+            let (_, h, _) = object.dims();
+            let top = (0..h as i32).rev()
+                .find(|&y| object.get(tx + gx, y, tz + gz) != 0)
+                .map(|y| y - gy)
+                .unwrap_or(ob.max_y);
+            ((tx, top + 1, tz), (sx, sb.min_y, sz))
+        }
+        _ => ((ox, ob.max_y + 1, oz), (sx, sb.min_y, sz)),   // top_of
+    };
+
+    // The part's own `anchor` overrides the side
+    let point = match rel.args.iter().find(|a| a.key == "anchor").map(|a| &a.value) {
+        Some(Expr::Ident(i)) if i.name == "base"   => (sx, sb.min_y, sz),
+        Some(Expr::Ident(i)) if i.name == "top"    => (sx, sb.max_y, sz),
+        Some(Expr::Ident(i)) if i.name == "center" => (sx, sy, sz),
+        Some(Expr::Tuple(items)) if items.len() == 3 => voxel_point(items, voxel_size),
+        _ => side,
+    };
+
+    PartOffset {
+        dx: obj_off.dx + target.0 - point.0,
+        dy: obj_off.dy + target.1 - point.1,
+        dz: obj_off.dz + target.2 - point.2,
+    }
+}
+
+// ── Qualifiers ─────────────────────────────────────────────────────────────
+//
+// `Head above Spine gap=1, flush_front, offset=(0, 0, 1)`.  Lengths are in
+// world units; `Qualifiers::of` turns them into voxels.
+
+/// A length in world units as a whole number of voxels; anything but a
+/// number is 0.
+pub fn voxels(e: &Expr, voxel_size: f64) -> i32 {
+    match e {
+        Expr::Int(n)   => (*n as f64 / voxel_size).round() as i32,
+        Expr::Float(f) => (f / voxel_size).round() as i32,
+        _              => 0,
+    }
+}
+
+/// An `(x, y, z)` tuple of world lengths in voxels.
+fn voxel_point(items: &[Expr], voxel_size: f64) -> (i32, i32, i32) {
+    (voxels(&items[0], voxel_size), voxels(&items[1], voxel_size), voxels(&items[2], voxel_size))
+}
+
+/// How a relation lines the subject up with the object on an axis it does
+/// not push along.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Align {
+    /// Centres aligned (`centered`, the default).
+    #[default]
+    Centre,
+    /// Low sides flush: `flush_left` (x) or `flush_front` (z).
+    Min,
+    /// High sides flush: `flush_right` (x) or `flush_back` (z).
+    Max,
+}
+
+/// The qualifiers of one relation, with lengths in voxels.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Qualifiers {
+    pub gap:    i32,                // `gap = n`, or minus `overlap = n`
+    pub offset: (i32, i32, i32),    // `offset = (x, y, z)`, added last
+    pub x:      Align,
+    pub z:      Align,
+    /// Rest on the object's voxels rather than its bounding box: `contact`
+    /// or `bbox`, defaulting by relation (`contact_by_default`).
+    pub contact: bool,
+}
+
+impl Qualifiers {
+    /// Read the qualifiers of `rel`; anything the resolver would reject is
+    /// ignored.
+    pub fn of(rel: &RelationStmt, voxel_size: f64) -> Self {
+        let mut q = Qualifiers { contact: contact_by_default(&rel.predicate), ..Qualifiers::default() };
+        for arg in &rel.args {
+            match (arg.key.as_str(), &arg.value) {
+                ("gap", v)     => q.gap = voxels(v, voxel_size),
+                ("overlap", v) => q.gap = -voxels(v, voxel_size),
+                ("offset", Expr::Tuple(items)) if items.len() == 3 => q.offset = voxel_point(items, voxel_size),
+                _ => {}
+            }
+        }
+        for word in &rel.qualifiers {
+            match word.name.as_str() {
+                "flush_left"  => q.x = Align::Min,
+                "flush_right" => q.x = Align::Max,
+                "flush_front" => q.z = Align::Min,
+                "flush_back"  => q.z = Align::Max,
+                "contact"     => q.contact = true,
+                "bbox"        => q.contact = false,
+                _ => {}
+            }
+        }
+        q
+    }
+}
+
+/// Relations that rest on the object's voxels unless told `bbox`: `above`,
+/// the contact relations, and `on` / `across` in a layout.  The rest stack
+/// bounding boxes unless told `contact`.
+pub fn contact_by_default(kind: &RelationKind) -> bool {
+    matches!(kind,
+        RelationKind::Above | RelationKind::Touch | RelationKind::AdjacentTo | RelationKind::AttachedTo
+        | RelationKind::On | RelationKind::Across)
+}
+
+/// Where along `axis` a subject grid comes to rest against an object grid
+/// when it slides in from the `sign` side: the subject's shift on that axis
+/// at which its voxels first touch the object's.  Shifts map grid
+/// coordinates to world ones; the subject's shift on the other two axes is
+/// kept.  `None` when no column along the axis holds both, so the subject
+/// would slide past.
+pub fn contact_position(
+    subject:       &VoxelGrid,
+    subject_shift: (i32, i32, i32),
+    object:        &VoxelGrid,
+    object_shift:  (i32, i32, i32),
+    axis:          usize,
+    sign:          i32,
+) -> Option<i32> {
+    // For each column across the axis: the extent of the grid along it
+    let columns = |grid: &VoxelGrid, (sx, sy, sz): (i32, i32, i32), along_shift: bool| {
+        let mut out: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+        for (x, y, z, _) in grid.iter_filled() {
+            let w = [x as i32 + sx, y as i32 + sy, z as i32 + sz];
+            let along = if along_shift { w[axis] } else { [x as i32, y as i32, z as i32][axis] };
+            let col = match axis { 0 => (w[1], w[2]), 1 => (w[0], w[2]), _ => (w[0], w[1]) };
+            let e = out.entry(col).or_insert((along, along));
+            e.0 = e.0.min(along);
+            e.1 = e.1.max(along);
+        }
+        out
+    };
+    let theirs = columns(object, object_shift, true);
+    let ours   = columns(subject, subject_shift, false);
+
+    // Rest against the tallest column in the way
+    let rests = ours.iter().filter_map(|(col, &(lo, hi))| {
+        let &(olo, ohi) = theirs.get(col)?;
+        Some(if sign > 0 { ohi + 1 - lo } else { olo - 1 - hi })
+    });
+    if sign > 0 { rests.max() } else { rests.min() }
+}
+
+/// The axis (0 = x, 1 = y, 2 = z) and direction a relation pushes its
+/// subject away from the object along — where `gap` and `overlap` apply.
+/// `None` for relations that centre or mirror instead.
+pub fn push_direction(kind: &RelationKind) -> Option<(usize, i32)> {
+    match kind {
+        RelationKind::Above | RelationKind::On | RelationKind::Across | RelationKind::Touch
+        | RelationKind::AdjacentTo | RelationKind::AttachedTo => Some((1, 1)),
+        RelationKind::Below                                   => Some((1, -1)),
+        RelationKind::LeftOf                                  => Some((0, -1)),
+        RelationKind::RightOf | RelationKind::Outside         => Some((0, 1)),
+        RelationKind::InFrontOf                               => Some((2, -1)),
+        RelationKind::Behind                                  => Some((2, 1)),
+        RelationKind::Inside | RelationKind::Surrounds
+        | RelationKind::SymmetricAcross                       => None,
+    }
+}
+
+// ── Solver ─────────────────────────────────────────────────────────────────
+//
+// Relations form a graph: the part a relation places depends on its object.
+// Parts are placed from the roots (parts that are never placed by a
+// relation) outwards, so a chain of any length settles in one pass and
+// statement order does not matter.  Cycles and parts pulled two ways on one
+// axis are rejected by `check_relations`.
+//
+// Most relations place their subject.  `symmetric_across` places a mirrored
+// copy of its subject instead — the subject's `mirror_of` part on the same
+// axis if it has one, else an unnamed copy — which waits for both the
+// subject and the object.
+
+/// Axes (x, y, z) a relation fixes on the part it places between parts; the
+/// others it only aligns with the object.  `symmetric_across` places its
+/// mirrored copy outright.
+pub fn fixed_axes(kind: &RelationKind) -> [bool; 3] {
+    match kind {
+        RelationKind::Above | RelationKind::Below | RelationKind::On | RelationKind::Across
+        | RelationKind::Touch | RelationKind::AdjacentTo | RelationKind::AttachedTo => [false, true, false],
+        RelationKind::LeftOf | RelationKind::RightOf | RelationKind::Outside  => [true, false, false],
+        RelationKind::InFrontOf | RelationKind::Behind                        => [false, false, true],
+        RelationKind::Inside | RelationKind::Surrounds
+        | RelationKind::SymmetricAcross                                       => [true, true, true],
+    }
+}
+
+/// The `axis = x|y|z` of a `symmetric_across` (default x).
+pub fn mirror_axis(rel: &RelationStmt) -> Axis {
+    rel.args.iter()
+        .find(|a| a.key == "axis")
+        .and_then(|a| match &a.value { Expr::Ident(i) => Axis::parse(&i.name), _ => None })
+        .unwrap_or(Axis::X)
+}
+
+/// The part a relation places between parts.  `mirrors` lists the entity's
+/// `mirror_of` parts as (name, source, axis).
+pub fn placed_part(rel: &RelationStmt, mirrors: &[(String, String, Axis)]) -> String {
+    if rel.predicate != RelationKind::SymmetricAcross {
+        return rel.subject.name.clone();
+    }
+    let axis = mirror_axis(rel);
+    mirrors.iter()
+        .find(|(_, source, a)| *source == rel.subject.name && *a == axis)
+        .map(|(name, _, _)| name.clone())
+        .unwrap_or_else(|| format!("{} (mirrored across {}, {axis:?})", rel.subject.name, rel.object.name))
+}
+
+/// Relations in the order the solver applies them, grouped by the part they
+/// place (`owner`): each group after every part it depends on (ties by
+/// name), and within a group by object name with `symmetric_across` last.
+/// Relations on a cycle, and relations placing their own object, are left
+/// out.
+pub fn solve_order(
+    relations: &[RelationStmt],
+    owner:     impl Fn(&RelationStmt) -> String,
+) -> Vec<(String, Vec<&RelationStmt>)> {
+    let relations: Vec<(String, &RelationStmt)> = relations.iter()
+        .map(|r| (owner(r), r))
+        .filter(|(placed, r)| *placed != r.object.name)
+        .collect();
+
+    // Parts each placed part still waits for
+    let mut waiting: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+    for (placed, rel) in &relations {
+        let needs = waiting.entry(placed.as_str()).or_default();
+        needs.insert(rel.object.name.as_str());
+        if *placed != rel.subject.name {
+            needs.insert(rel.subject.name.as_str());
+            waiting.entry(rel.subject.name.as_str()).or_default();
+        }
+        waiting.entry(rel.object.name.as_str()).or_default();
+    }
+    let mut ready: BTreeSet<&str> = waiting.iter()
+        .filter(|(_, needs)| needs.is_empty())
+        .map(|(&name, _)| name)
+        .collect();
+
+    let mut ordered = Vec::new();
+    while let Some(name) = ready.pop_first() {
+        let mut group: Vec<&RelationStmt> = relations.iter()
+            .filter(|(placed, _)| placed == name)
+            .map(|&(_, r)| r)
+            .collect();
+        if !group.is_empty() {
+            group.sort_by(|a, b| {
+                let mirror = |r: &RelationStmt| r.predicate == RelationKind::SymmetricAcross;
+                (mirror(a), &a.object.name).cmp(&(mirror(b), &b.object.name))
+            });
+            ordered.push((name.to_string(), group));
+        }
+
+        for (&placed, needs) in waiting.iter_mut() {
+            if needs.remove(name) && needs.is_empty() {
+                ready.insert(placed);
+            }
+        }
+    }
+    ordered
+}
+
+/// Cycles and over-constrained parts among `relations`.  `owner` gives the
+/// part each relation places and `fixed` the axes it fixes there; two
+/// relations that fix the same axis of one part conflict, unless they are
+/// the same relation written twice.
+pub fn check_relations(
+    relations: &[RelationStmt],
+    fixed:     fn(&RelationKind) -> [bool; 3],
+    owner:     impl Fn(&RelationStmt) -> String,
+) -> Vec<MoxiError> {
+    let mut errors = Vec::new();
+    let owners: Vec<String> = relations.iter().map(&owner).collect();
+
+    for (j, later) in relations.iter().enumerate() {
+        let clash = relations[..j].iter().zip(&owners).find_map(|(r, placed)| {
+            if *placed != owners[j]
+                || (r.predicate == later.predicate && r.subject.name == later.subject.name
+                    && r.object.name == later.object.name)
+            {
+                return None;
+            }
+            let axis = (0..3).find(|&i| fixed(&r.predicate)[i] && fixed(&later.predicate)[i])?;
+            Some((r, ['x', 'y', 'z'][axis]))
+        });
+        if let Some((earlier, axis)) = clash {
+            errors.push(MoxiError::OverConstrained {
+                name:  owners[j].clone(),
+                axis,
+                first: earlier.span,
+                span:  later.span,
+            });
+        }
+    }
+
+    // Edges placed part → part it waits for
+    let mut edges: Vec<(&str, &str, &RelationStmt)> = Vec::new();
+    for (rel, placed) in relations.iter().zip(&owners) {
+        edges.push((placed, &rel.object.name, rel));
+        if *placed != rel.subject.name {
+            edges.push((placed, &rel.subject.name, rel));
+        }
+    }
+
+    // Depth-first over the edges; 1 = on the current path, 2 = done.
+    // Each cycle is reported once, at the relation that closes it.
+    fn visit<'a>(
+        name:   &'a str,
+        edges:  &[(&'a str, &'a str, &RelationStmt)],
+        state:  &mut HashMap<&'a str, u8>,
+        path:   &mut Vec<&'a str>,
+        errors: &mut Vec<MoxiError>,
+    ) {
+        state.insert(name, 1);
+        path.push(name);
+        for &(_, next, rel) in edges.iter().filter(|(from, _, _)| *from == name) {
+            match state.get(next) {
+                Some(2) => {}
+                Some(_) => {
+                    let from = path.iter().position(|&p| p == next).unwrap_or(0);
+                    let mut cycle: Vec<String> = path[from..].iter().map(|p| p.to_string()).collect();
+                    cycle.push(next.to_string());
+                    errors.push(MoxiError::DependencyCycle { cycle, span: rel.span });
+                }
+                None => visit(next, edges, state, path, errors),
+            }
+        }
+        path.pop();
+        state.insert(name, 2);
+    }
+
+    let mut state = HashMap::new();
+    for &(from, _, _) in &edges {
+        if !state.contains_key(from) {
+            visit(from, &edges, &mut state, &mut Vec::new(), &mut errors);
+        }
+    }
+    errors
+}
+
+// ── World layout ───────────────────────────────────────────────────────────
+//
+// `layout { … }` relations position whole entity grids in world space.  The
+// same relations as between parts, with two differences: lateral relations
+// keep the subject's base level with the object's (entities stand on the same
+// ground), and `on` / `across` rest the subject on the object's actual
+// surface under its footprint rather than on its bounding box.
+
+/// World offset for the grid `subject` so that it stands in `rel` to the
+/// grid `object`, already placed at `object_offset`.  `subject_offset` is
+/// where the subject is now (only `symmetric_across` reads it).
+pub fn layout_offset(
+    rel:            &RelationStmt,
+    subject:        &VoxelGrid,
+    subject_offset: (i32, i32, i32),
+    object:         &VoxelGrid,
+    object_offset:  (i32, i32, i32),
+    voxel_size:     f64,
+) -> (i32, i32, i32) {
+    let q   = Qualifiers::of(rel, voxel_size);
+    let gap = q.gap;
+
+    let sb = BBox::from_grid(subject);
+    let ob = BBox::from_grid(object).shifted(object_offset);
+
+    // Offsets that line the subject's centre (or a flush side) / base up
+    // with the object's
+    let cx = match q.x {
+        Align::Centre => (ob.min_x + ob.max_x) / 2 - (sb.min_x + sb.max_x) / 2,
+        Align::Min    => ob.min_x - sb.min_x,
+        Align::Max    => ob.max_x - sb.max_x,
+    };
+    let cy = (ob.min_y + ob.max_y) / 2 - (sb.min_y + sb.max_y) / 2;
+    let cz = match q.z {
+        Align::Centre => (ob.min_z + ob.max_z) / 2 - (sb.min_z + sb.max_z) / 2,
+        Align::Min    => ob.min_z - sb.min_z,
+        Align::Max    => ob.max_z - sb.max_z,
+    };
+    let base = ob.min_y - sb.min_y;
+
+    let (dx, dy, dz) = match rel.predicate {
+        RelationKind::Above => (cx, ob.max_y + 1 + gap - sb.min_y, cz),
+        RelationKind::Below => (cx, ob.min_y - 1 - gap - sb.max_y, cz),
+
+        RelationKind::Inside | RelationKind::Surrounds => (cx, cy, cz),
+
+        RelationKind::LeftOf    => (ob.min_x - 1 - gap - sb.max_x, base, cz),
+        RelationKind::RightOf
+        | RelationKind::Outside => (ob.max_x + 1 + gap - sb.min_x, base, cz),
+        RelationKind::InFrontOf => (cx, base, ob.min_z - 1 - gap - sb.max_z),
+        RelationKind::Behind    => (cx, base, ob.max_z + 1 + gap - sb.min_z),
+
+        RelationKind::SymmetricAcross => {
+            // Mirror the subject's centre across the object's centre
+            let (x, y, z) = subject_offset;
+            let mirror = |d: i32, (omin, omax): (i32, i32), (smin, smax): (i32, i32)| {
+                let centre = d + (smin + smax) / 2;
+                (omin + omax) - centre - (smin + smax) / 2
+            };
+            match mirror_axis(rel) {
+                Axis::X => (mirror(x, (ob.min_x, ob.max_x), (sb.min_x, sb.max_x)), y, z),
+                Axis::Y => (x, mirror(y, (ob.min_y, ob.max_y), (sb.min_y, sb.max_y)), z),
+                Axis::Z => (x, y, mirror(z, (ob.min_z, ob.max_z), (sb.min_z, sb.max_z))),
+            }
+        }
+
+        RelationKind::On | RelationKind::Across | RelationKind::Touch
+        | RelationKind::AdjacentTo | RelationKind::AttachedTo => {
+            let (dx, dz) = (cx, cz);
+            let top = if q.contact {
+                surface_under(subject, (dx, dz), object, object_offset).unwrap_or(ob.max_y)
+            } else {
+                ob.max_y
+            };
+            (dx, top + 1 + gap - sb.min_y, dz)
+        }
     };
 
-    offsets.insert(subj.to_string(), new_off);
+    // `contact` on a stacking relation: slide onto the object's voxels
+    let mut at = [dx, dy, dz];
+    let surface = matches!(rel.predicate,
+        RelationKind::On | RelationKind::Across | RelationKind::Touch
+        | RelationKind::AdjacentTo | RelationKind::AttachedTo);
+    if let (true, false, Some((axis, sign))) = (q.contact, surface, push_direction(&rel.predicate)) {
+        if let Some(rest) = contact_position(subject, (dx, dy, dz), object, object_offset, axis, sign) {
+            at[axis] = rest + sign * gap;
+        }
+    }
+    (at[0] + q.offset.0, at[1] + q.offset.1, at[2] + q.offset.2)
+}
+
+/// `Bridge across River`: turn `subject` so its long side crosses the
+/// route at the route's midpoint, and rest it on the banks of `terrain`
+/// (placed at `terrain_offset`).  Returns the turned grid and its world
+/// offset, or `None` for a route with no cells.
+pub fn span_route(
+    rel:            &RelationStmt,
+    subject:        &VoxelGrid,
+    route:          &CarvedRoute,
+    terrain:        &VoxelGrid,
+    terrain_offset: (i32, i32, i32),
+    voxel_size:     f64,
+) -> Option<(VoxelGrid, (i32, i32, i32))> {
+    let cells = &route.cells;
+    if cells.is_empty() { return None; }
+    let mid = cells.len() / 2;
+    let (ax, az) = cells[mid.saturating_sub(1)];
+    let (bx, bz) = cells[(mid + 1).min(cells.len() - 1)];
+    let flows_along_x = (bx - ax).abs() >= (bz - az).abs();
+
+    let (w, _, d) = subject.dims();
+    let grid = if flows_along_x == (w >= d) { subject.rotated(Axis::Y, 1) } else { subject.clone() };
+
+    let sb = BBox::from_grid(&grid);
+    let (cx, cz) = cells[mid];
+    let dx = cx - (sb.min_x + sb.max_x) / 2;
+    let dz = cz - (sb.min_z + sb.max_z) / 2;
+    let top = surface_under(&grid, (dx, dz), terrain, terrain_offset).unwrap_or(terrain_offset.1);
+    let q = Qualifiers::of(rel, voxel_size);
+    Some((grid, (dx + q.offset.0, top + 1 + q.gap - sb.min_y + q.offset.1, dz + q.offset.2)))
+}
+
+/// Axes a layout relation fixes: all of them — the subject's whole offset
+/// comes from its object — except `symmetric_across`, which mirrors it.
+pub fn layout_fixed_axes(kind: &RelationKind) -> [bool; 3] {
+    match kind {
+        RelationKind::SymmetricAcross => [false, false, false],
+        _                             => [true, true, true],
+    }
+}
+
+/// Highest world y of `object` under the filled columns of `subject` when
+/// the subject sits at horizontal offset `(dx, dz)`.
+pub fn surface_under(
+    subject:       &VoxelGrid,
+    (dx, dz):      (i32, i32),
+    object:        &VoxelGrid,
+    object_offset: (i32, i32, i32),
+) -> Option<i32> {
+    let (ox, oy, oz) = object_offset;
+    let (_, oh, _) = object.dims();
+    let mut columns: Vec<(i32, i32)> = subject.iter_filled()
+        .map(|(x, _, z, _)| (x as i32 + dx - ox, z as i32 + dz - oz))
+        .collect();
+    columns.sort_unstable();
+    columns.dedup();
+    columns.into_iter()
+        .filter_map(|(x, z)| (0..oh as i32).rev().find(|&y| object.get(x, y, z) != 0))
+        .max()
+        .map(|y| y + oy)
 }
 
 // ── Bounding box ───────────────────────────────────────────────────────────
@@ -195,9 +897,9 @@ impl BBox {
 
         for (x, y, z, _) in part.grid.iter_filled() {
             let (x, y, z) = (x as i32, y as i32, z as i32);
-            if x < min_x { min_x = x; } if x > max_x { max_x = x; }
-            if y < min_y { min_y = y; } if y > max_y { max_y = y; }
-            if z < min_z { min_z = z; } if z > max_z { max_z = z; }
+            min_x = min_x.min(x); max_x = max_x.max(x);
+            min_y = min_y.min(y); max_y = max_y.max(y);
+            min_z = min_z.min(z); max_z = max_z.max(z);
         }
 
         // Empty grid fallback
@@ -219,6 +921,35 @@ impl BBox {
         }
     }
 
+    /// Bounds of the filled voxels of a grid, in grid coordinates (not
+    /// centred, unlike `from_part`).
+    pub fn from_grid(grid: &VoxelGrid) -> Self {
+        let mut b = Self {
+            min_x: i32::MAX, max_x: i32::MIN,
+            min_y: i32::MAX, max_y: i32::MIN,
+            min_z: i32::MAX, max_z: i32::MIN,
+        };
+        for (x, y, z, _) in grid.iter_filled() {
+            let (x, y, z) = (x as i32, y as i32, z as i32);
+            b.min_x = b.min_x.min(x); b.max_x = b.max_x.max(x);
+            b.min_y = b.min_y.min(y); b.max_y = b.max_y.max(y);
+            b.min_z = b.min_z.min(z); b.max_z = b.max_z.max(z);
+        }
+        if b.min_x == i32::MAX {
+            return Self { min_x:0, max_x:0, min_y:0, max_y:0, min_z:0, max_z:0 };
+        }
+        b
+    }
+
+    /// The same box moved by `(dx, dy, dz)`.
+    pub fn shifted(&self, (dx, dy, dz): (i32, i32, i32)) -> Self {
+        Self {
+            min_x: self.min_x + dx, max_x: self.max_x + dx,
+            min_y: self.min_y + dy, max_y: self.max_y + dy,
+            min_z: self.min_z + dz, max_z: self.max_z + dz,
+        }
+    }
+
     pub fn width(&self)  -> i32 { self.max_x - self.min_x + 1 }
     pub fn height(&self) -> i32 { self.max_y - self.min_y + 1 }
     pub fn depth(&self)  -> i32 { self.max_z - self.min_z + 1 }
@@ -226,4 +957,388 @@ impl BBox {
     pub fn center_x(&self) -> i32 { (self.min_x + self.max_x) / 2 }
     pub fn center_y(&self) -> i32 { (self.min_y + self.max_y) / 2 }
     pub fn center_z(&self) -> i32 { (self.min_z + self.max_z) / 2 }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{CarveKind, Ident, Prop};
+    use crate::error::Span;
+
+    fn ident(name: &str) -> Ident {
+        Ident { name: name.to_string(), span: Span::new(1, 1) }
+    }
+
+    /// A solid `w`×`h`×`d` box part.
+    fn block(name: &str, (w, h, d): (i32, i32, i32)) -> CompiledPart {
+        let mut grid = VoxelGrid::new(w as u32, h as u32, d as u32);
+        for z in 0..d {
+            for y in 0..h {
+                for x in 0..w { grid.set(x, y, z, 1); }
+            }
+        }
+        CompiledPart { name: name.to_string(), grid, atom_id: 1, voxel_size: 1.0, mirror_of: None }
+    }
+
+    fn rel(subject: &str, predicate: RelationKind, object: &str, args: &[(&str, i64)]) -> RelationStmt {
+        RelationStmt {
+            subject:    ident(subject),
+            predicate,
+            object:     ident(object),
+            qualifiers: Vec::new(),
+            args:       args.iter()
+                .map(|&(key, n)| Prop { key: key.to_string(), value: Expr::Int(n), span: Span::new(1, 1) })
+                .collect(),
+            span:       Span::new(1, 1),
+        }
+    }
+
+    /// Lowest and highest world y of a part's voxels.
+    fn y_range(parts: &[CompiledPart], offsets: &OffsetMap, name: &str) -> (i32, i32) {
+        let part = parts.iter().find(|p| p.name == name).unwrap();
+        let b = BBox::from_part(part);
+        let dy = offsets[name].dy;
+        (b.min_y + dy, b.max_y + dy)
+    }
+
+    #[test]
+    fn above_rests_the_base_on_the_top() {
+        let parts = [block("Base", (3, 3, 3)), block("Head", (3, 3, 3))];
+        let offsets = resolve_offsets(&parts, &[rel("Head", RelationKind::Above, "Base", &[])], 1.0);
+        let (_, base_top)  = y_range(&parts, &offsets, "Base");
+        let (head_low, _)  = y_range(&parts, &offsets, "Head");
+        assert_eq!(head_low, base_top + 1);
+    }
+
+    #[test]
+    fn above_gap_leaves_that_many_empty_voxels() {
+        let parts = [block("Base", (3, 3, 3)), block("Head", (3, 5, 3))];
+        let offsets = resolve_offsets(&parts, &[rel("Head", RelationKind::Above, "Base", &[("gap", 2)])], 1.0);
+        let (_, base_top) = y_range(&parts, &offsets, "Base");
+        let (head_low, _) = y_range(&parts, &offsets, "Head");
+        assert_eq!(head_low - base_top - 1, 2);
+    }
+
+    /// Lowest and highest world x of a part's voxels.
+    fn x_range(parts: &[CompiledPart], offsets: &OffsetMap, name: &str) -> (i32, i32) {
+        let part = parts.iter().find(|p| p.name == name).unwrap();
+        let b = BBox::from_part(part);
+        let dx = offsets[name].dx;
+        (b.min_x + dx, b.max_x + dx)
+    }
+
+    #[test]
+    fn left_of_gap_leaves_that_many_empty_voxels() {
+        let parts = [block("Spine", (3, 9, 3)), block("LeftArm", (5, 2, 2)), block("RightArm", (5, 2, 2))];
+        let offsets = resolve_offsets(&parts, &[
+            rel("LeftArm",  RelationKind::LeftOf,  "Spine", &[]),
+            rel("RightArm", RelationKind::RightOf, "Spine", &[]),
+        ], 1.0);
+        let (spine_left, spine_right) = x_range(&parts, &offsets, "Spine");
+        assert_eq!(x_range(&parts, &offsets, "LeftArm").1, spine_left - 1);
+        assert_eq!(x_range(&parts, &offsets, "RightArm").0, spine_right + 1);
+
+        let offsets = resolve_offsets(&parts, &[rel("LeftArm", RelationKind::LeftOf, "Spine", &[("gap", 1)])], 1.0);
+        let (spine_left, _) = x_range(&parts, &offsets, "Spine");
+        let (_, arm_right)  = x_range(&parts, &offsets, "LeftArm");
+        assert_eq!(spine_left - arm_right - 1, 1);
+    }
+
+    #[test]
+    fn above_rests_on_voxels_unless_told_bbox() {
+        // A dimple in the middle of the base's top
+        let mut base = block("Base", (3, 3, 3));
+        base.grid.set(1, 2, 1, 0);
+        let parts = [base, block("Ball", (1, 1, 1))];
+
+        let offsets = resolve_offsets(&parts, &[rel("Ball", RelationKind::Above, "Base", &[])], 1.0);
+        let (_, base_top) = y_range(&parts, &offsets, "Base");
+        assert_eq!(y_range(&parts, &offsets, "Ball").0, base_top);
+
+        let mut boxed = rel("Ball", RelationKind::Above, "Base", &[]);
+        boxed.qualifiers.push(ident("bbox"));
+        let offsets = resolve_offsets(&parts, &[boxed], 1.0);
+        assert_eq!(y_range(&parts, &offsets, "Ball").0, base_top + 1);
+    }
+
+    /// `P0 ← P1 ← … ← P5`, each part above the one before.
+    fn tower() -> (Vec<CompiledPart>, Vec<RelationStmt>) {
+        let parts: Vec<CompiledPart> = (0..6).map(|i| block(&format!("P{i}"), (3, 2, 3))).collect();
+        let relations = (1..6)
+            .map(|i| rel(&format!("P{i}"), RelationKind::Above, &format!("P{}", i - 1), &[]))
+            .collect();
+        (parts, relations)
+    }
+
+    #[test]
+    fn long_chains_settle_in_one_pass() {
+        let (parts, relations) = tower();
+        let offsets = resolve_offsets(&parts, &relations, 1.0);
+        for i in 1..6 {
+            let (_, below) = y_range(&parts, &offsets, &format!("P{}", i - 1));
+            let (above, _) = y_range(&parts, &offsets, &format!("P{i}"));
+            assert_eq!(above, below + 1, "P{i}");
+        }
+    }
+
+    #[test]
+    fn relation_order_does_not_matter() {
+        let (parts, relations) = tower();
+        let offsets = resolve_offsets(&parts, &relations, 1.0);
+        let mut shuffled = relations.clone();
+        shuffled.reverse();
+        shuffled.swap(1, 3);
+        let again = resolve_offsets(&parts, &shuffled, 1.0);
+        for part in &parts {
+            let (a, b) = (offsets[&part.name], again[&part.name]);
+            assert_eq!((a.dx, a.dy, a.dz), (b.dx, b.dy, b.dz), "{}", part.name);
+        }
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let relations = [
+            rel("A", RelationKind::Above, "B", &[]),
+            rel("B", RelationKind::LeftOf, "C", &[]),
+            rel("C", RelationKind::Behind, "A", &[]),
+        ];
+        let errors = check_relations(&relations, fixed_axes, |r| r.subject.name.clone());
+        assert!(matches!(errors.as_slice(), [MoxiError::DependencyCycle { cycle, .. }] if cycle.len() == 4),
+            "{errors:?}");
+    }
+
+    #[test]
+    fn two_relations_on_one_axis_are_over_constrained() {
+        let relations = [
+            rel("A", RelationKind::Above, "B", &[]),
+            rel("A", RelationKind::Below, "C", &[]),
+            rel("A", RelationKind::LeftOf, "C", &[]),
+        ];
+        let errors = check_relations(&relations, fixed_axes, |r| r.subject.name.clone());
+        assert!(matches!(errors.as_slice(),
+            [MoxiError::OverConstrained { name, axis: 'y', .. }] if name == "A"), "{errors:?}");
+
+        // The same relation written twice is not a conflict
+        let twice = [rel("A", RelationKind::Above, "B", &[]), rel("A", RelationKind::Above, "B", &[])];
+        assert!(check_relations(&twice, fixed_axes, |r| r.subject.name.clone()).is_empty());
+    }
+
+    #[test]
+    fn contact_rests_on_the_tallest_column_in_the_way() {
+        // Columns 1, 3 and 2 voxels tall
+        let mut object = VoxelGrid::new(3, 3, 1);
+        for (x, h) in [(0, 1), (1, 3), (2, 2)] {
+            for y in 0..h { object.set(x, y, 0, 1); }
+        }
+        let bar = block("Bar", (3, 1, 1)).grid;
+        assert_eq!(contact_position(&bar, (0, 0, 0), &object, (0, 0, 0), 1, 1), Some(3));
+        assert_eq!(contact_position(&bar, (0, 0, 0), &object, (0, 10, 0), 1, 1), Some(13));
+        // Hanging below: the lowest column bottom is 10 everywhere
+        assert_eq!(contact_position(&bar, (0, 0, 0), &object, (0, 10, 0), 1, -1), Some(9));
+
+        // A one-voxel subject over the short column only meets that column
+        let dot = block("Dot", (1, 1, 1)).grid;
+        assert_eq!(contact_position(&dot, (0, 0, 0), &object, (0, 0, 0), 1, 1), Some(1));
+        // Nothing in the way
+        assert_eq!(contact_position(&dot, (5, 0, 0), &object, (0, 0, 0), 1, 1), None);
+    }
+
+    fn mirror(subject: &str, object: &str, axis: &str) -> RelationStmt {
+        let mut r = rel(subject, RelationKind::SymmetricAcross, object, &[]);
+        r.args.push(Prop { key: "axis".to_string(), value: Expr::Ident(ident(axis)), span: Span::new(1, 1) });
+        r
+    }
+
+    fn filled(grid: &VoxelGrid) -> Vec<(u32, u32, u32, u16)> {
+        let mut voxels: Vec<_> = grid.iter_filled().collect();
+        voxels.sort();
+        voxels
+    }
+
+    #[test]
+    fn symmetric_across_draws_a_flipped_copy_on_the_far_side() {
+        // An arm with a notch at its outer end, so a copy that isn't flipped shows
+        let mut arm = block("LeftArm", (5, 2, 2));
+        arm.grid.set(0, 1, 0, 0);
+        let parts = [block("Spine", (3, 9, 3)), arm];
+        let relations = [
+            rel("LeftArm", RelationKind::LeftOf, "Spine", &[("gap", 1)]),
+            rel("LeftArm", RelationKind::SymmetricAcross, "Spine", &[]),
+        ];
+
+        let offsets = resolve_offsets(&parts, &relations, 1.0);
+        assert!(offsets.contains_key("LeftArm (mirrored across Spine, X)"));
+        // The whole entity reads the same in a mirror across the spine
+        let grid = assemble_entity(&parts, &relations, 1.0);
+        assert_eq!(grid.dims(), (3 + 2 * (5 + 1), 9, 3));
+        assert_eq!(filled(&grid.mirrored(Axis::X)), filled(&grid));
+    }
+
+    #[test]
+    fn symmetric_across_reflects_along_its_axis() {
+        let parts = [block("Body", (5, 5, 5)), block("Knob", (1, 2, 1))];
+        let reflected = |place: RelationKind, axis: &str, pick: fn(&BBox) -> (i32, i32), shift: fn(&PartOffset) -> i32| {
+            let offsets = resolve_offsets(&parts, &[rel("Knob", place, "Body", &[]), mirror("Knob", "Body", axis)], 1.0);
+            let range = |name: &str, copy: &str| {
+                let (lo, hi) = pick(&BBox::from_part(parts.iter().find(|p| p.name == name).unwrap()));
+                let d = shift(&offsets[copy]);
+                (lo + d, hi + d)
+            };
+            let (b0, b1) = range("Body", "Body");
+            let (k0, k1) = range("Knob", "Knob");
+            let copy = range("Knob", &format!("Knob (mirrored across Body, {})", axis.to_uppercase()));
+            assert_eq!(copy, (b0 + b1 - k1, b0 + b1 - k0), "axis {axis}");
+        };
+        reflected(RelationKind::Above,  "y", |b| (b.min_y, b.max_y), |o| o.dy);
+        reflected(RelationKind::Behind, "z", |b| (b.min_z, b.max_z), |o| o.dz);
+    }
+
+    #[test]
+    fn mirror_of_parts_follow_their_source() {
+        use crate::geometry::compile;
+        use crate::lexer::Lexer;
+        use crate::parser::Parser;
+        use crate::resolver::Resolver;
+
+        let build = |height: u32| {
+            let src = format!("
+                atom A {{ color = white }}
+                atom B {{ color = red }}
+                material Bone  {{ color = white, voxel_atom = A }}
+                material Blood {{ color = red,   voxel_atom = B }}
+                entity Cave {{
+                    part Tip  {{ shape = cone(radius=2, height={height}), material = Bone }}
+                    part Drip mirror_of Tip axis=y {{ material = Blood }}
+                }}
+            ");
+            let (tokens, _) = Lexer::new(&src).tokenize();
+            let (doc, parse_errors) = Parser::new(tokens).parse();
+            let (scene, errors) = Resolver::new().resolve(doc);
+            assert!(parse_errors.is_empty() && errors.is_empty(), "{parse_errors:?} {errors:?}");
+            compile(&scene).0.remove(0).parts
+        };
+
+        for height in [4, 6] {
+            let parts = build(height);
+            let [tip, drip] = &parts[..] else { panic!("{parts:?}") };
+            assert_eq!(drip.mirror_of, Some(("Tip".to_string(), Axis::Y)));
+            // Upside down, in its own material
+            assert_ne!(filled(&tip.grid), filled(&tip.grid.mirrored(Axis::Y)));
+            let recoloured: Vec<_> = filled(&tip.grid.mirrored(Axis::Y)).into_iter()
+                .map(|(x, y, z, _)| (x, y, z, 2))
+                .collect();
+            assert_eq!(filled(&drip.grid), recoloured);
+        }
+    }
+
+    /// `subject attach_to = function(object, uv…)`, as the resolver writes it.
+    fn attach(subject: &str, function: &str, object: &str, uv: &[f64], anchor: Option<Expr>) -> RelationStmt {
+        let mut r = rel(subject, attach_relation(function).unwrap(), object, &[]);
+        let positional = uv.iter().map(|&t| Expr::Float(t)).collect();
+        let call = Expr::Call { name: function.to_string(), positional, args: Vec::new() };
+        r.args.push(Prop { key: "attach".to_string(), value: call, span: Span::new(1, 1) });
+        if let Some(anchor) = anchor {
+            r.args.push(Prop { key: "anchor".to_string(), value: anchor, span: Span::new(1, 1) });
+        }
+        r
+    }
+
+    #[test]
+    fn attach_to_lands_the_facing_side_just_outside_the_face() {
+        let parts = [block("Trunk", (1, 5, 1)), block("Crown", (3, 3, 3)), block("Root", (3, 2, 3))];
+        let offsets = resolve_offsets(&parts, &[
+            attach("Crown", "top_of", "Trunk", &[], None),
+            attach("Root", "bottom_of", "Trunk", &[], None),
+        ], 1.0);
+        let (trunk_low, trunk_top) = y_range(&parts, &offsets, "Trunk");
+        assert_eq!(y_range(&parts, &offsets, "Crown").0, trunk_top + 1);
+        assert_eq!(y_range(&parts, &offsets, "Root").1, trunk_low - 1);
+        assert_eq!(x_range(&parts, &offsets, "Crown"), (-1, 1));
+    }
+
+    #[test]
+    fn surface_at_finds_the_highest_voxel_at_u_v() {
+        // A slab with one tall column at its (min x, max z) corner
+        let mut ground = block("Ground", (5, 4, 5));
+        for z in 0..5 {
+            for x in 0..5 {
+                if (x, z) == (0, 4) { continue; }
+                for y in 2..4 { ground.grid.set(x, y, z, 0); }
+            }
+        }
+        let parts = [ground, block("Bird", (1, 1, 1))];
+        let offsets = resolve_offsets(&parts, &[attach("Bird", "surface_at", "Ground", &[0.0, 1.0], None)], 1.0);
+
+        let b = BBox::from_part(&parts[0]);
+        let bird = offsets["Bird"];
+        assert_eq!((bird.dx, bird.dz), (b.min_x, b.max_z));
+        assert_eq!(y_range(&parts, &offsets, "Bird").0, y_range(&parts, &offsets, "Ground").1 + 1);
+    }
+
+    #[test]
+    fn anchor_picks_the_point_that_lands() {
+        let parts = [block("Pole", (1, 3, 1)), block("Flag", (1, 5, 1))];
+        let (_, pole_top) = y_range(&parts, &resolve_offsets(&parts, &[], 1.0), "Pole");
+        let flag = |anchor: Expr| {
+            let offsets = resolve_offsets(&parts, &[attach("Flag", "top_of", "Pole", &[], Some(anchor))], 1.0);
+            y_range(&parts, &offsets, "Flag")
+        };
+        let point = |x, y, z| Expr::Tuple(vec![Expr::Int(x), Expr::Int(y), Expr::Int(z)]);
+
+        assert_eq!(flag(Expr::Ident(ident("base"))), (pole_top + 1, pole_top + 5));
+        assert_eq!(flag(Expr::Ident(ident("top"))),  (pole_top - 3, pole_top + 1));
+        assert_eq!(flag(Expr::Ident(ident("center"))), (pole_top - 1, pole_top + 3));
+        // Two voxels below the centre is the base
+        assert_eq!(flag(point(0, -2, 0)), flag(Expr::Ident(ident("base"))));
+    }
+
+    #[test]
+    fn layout_on_terrain_rests_on_the_surface_under_the_footprint() {
+        // Ground two voxels deep, with a post in one corner that sets the
+        // bounding box's top well above it
+        let mut terrain = block("Ground", (9, 6, 9)).grid;
+        for z in 0..9 {
+            for x in 0..9 {
+                for y in 2..6 {
+                    if (x, z) != (0, 0) { terrain.set(x, y, z, 0); }
+                }
+            }
+        }
+        let hut = block("Hut", (1, 1, 1)).grid;
+        let on = rel("Hut", RelationKind::On, "terrain", &[]);
+        assert_eq!(layout_offset(&on, &hut, (0, 0, 0), &terrain, (-4, 10, -4), 1.0), (0, 12, 0));
+    }
+
+    #[test]
+    fn layout_left_of_gap_leaves_that_many_empty_voxels() {
+        let hut   = block("Hut", (2, 2, 2)).grid;
+        let tower = block("Tower", (3, 3, 3)).grid;
+        let left = rel("Hut", RelationKind::LeftOf, "Tower", &[("gap", 2)]);
+        // Hut spans x 6..=7, Tower starts at 10: x 8 and 9 stay empty.  Both
+        // stand on the same ground, centred in depth.
+        assert_eq!(layout_offset(&left, &hut, (0, 0, 0), &tower, (10, 0, 0), 1.0), (6, 0, 1));
+    }
+
+    #[test]
+    fn span_route_turns_the_subject_across_and_rests_it_on_the_banks() {
+        // A river running along x at z = 5, in a trench from z 4 to 6
+        let mut terrain = block("Ground", (9, 3, 11)).grid;
+        for z in 4..=6 {
+            for x in 0..9 {
+                for y in 1..3 { terrain.set(x, y, z, 0); }
+            }
+        }
+        let route = CarvedRoute::new("River".to_string(), CarveKind::River, (0..9).map(|x| (x, 5)).collect());
+
+        // A bridge long in x is turned to run along z, centred on the
+        // route's middle cell and resting on the banks at y 2
+        let bridge = block("Bridge", (5, 1, 1)).grid;
+        let across = rel("Bridge", RelationKind::Across, "River", &[]);
+        let (grid, offset) = span_route(&across, &bridge, &route, &terrain, (0, 0, 0), 1.0).unwrap();
+        assert_eq!(grid.dims(), (1, 1, 5));
+        assert_eq!(offset, (4, 3, 3));
+
+        let empty = CarvedRoute::new("Dry".to_string(), CarveKind::River, Vec::new());
+        assert!(span_route(&across, &bridge, &empty, &terrain, (0, 0, 0), 1.0).is_none());
+    }
+}
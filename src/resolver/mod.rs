@@ -1,12 +1,19 @@
 use std::collections::HashMap;
 
+use crate::analysis::MEASURES;
 use crate::ast::*;
 use crate::error::{MoxiError, Span};
+use crate::geom::Axis;
+use crate::relation_resolver::{
+    attach_relation, check_relations, fixed_axes, layout_fixed_axes, placed_part, push_direction, voxels,
+};
 
 #[derive(Debug, Clone)]
 pub struct ResolvedAtom {
-    pub name:  String,
-    pub color: String,
+    pub name:    String,
+    pub color:   String,
+    /// Mass per cubic world unit, from `density = 2.6` (default 1.0).
+    pub density: f64,
 }
 
 #[derive(Debug, Clone)]
@@ -22,7 +29,9 @@ pub struct ResolvedPart {
     pub name:           String,
     pub shape:          Option<ShapeExpr>,
     pub material_index: Option<usize>,
-    pub anchor:         Option<String>,
+    /// `mirror_of Source axis=…` — a flipped copy of another part.
+    pub mirror_of:      Option<(String, Axis)>,
+    pub anchor:         Option<Expr>,
     pub attach_to:      Option<AttachSpec>,
 }
 
@@ -33,15 +42,159 @@ pub struct ResolvedEntity {
     pub relations:   Vec<RelationStmt>,
     pub constraints: Vec<ConstraintStmt>,
     pub resolve:     Option<ResolveOpts>,
+    pub grounding:   Grounding,
+}
+
+/// What happens to pieces of an entity that don't connect to its ground:
+/// `resolve floating = warn | error | remove | remove(max = n) | drop`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FloatingPolicy {
+    #[default]
+    Warn,
+    Error,
+    /// Delete pieces of at most `max` voxels (default 8); warn about bigger
+    /// ones, which are more likely a mistake than a speck.
+    Remove { max: usize },
+    /// Let them fall until they rest on something.
+    Drop,
+}
+
+/// Largest piece a bare `resolve floating = remove` deletes, in voxels.
+pub const REMOVE_MAX: usize = 8;
+
+/// An entity's `resolve floating / connectivity / ground` settings.
+#[derive(Debug, Clone, Default)]
+pub struct Grounding {
+    pub policy:   FloatingPolicy,
+    /// `connectivity = 26`: voxels sharing an edge or corner are joined,
+    /// not only those sharing a face.
+    pub diagonal: bool,
+    /// `ground = Part`: pieces touching this part are grounded.  Otherwise
+    /// the entity's lowest layer is the ground.
+    pub ground:   Option<String>,
+}
+
+/// A `river` or `path` with its route endpoints as world (x, z) columns:
+/// the script's world-unit positions divided by the world voxel size.
+#[derive(Debug, Clone)]
+pub struct ResolvedCarve {
+    pub name:           String,
+    pub kind:           CarveKind,
+    pub from:           (i32, i32),
+    pub to:             CarveTarget,
+    pub width:          f64,
+    pub depth:          i32,
+    pub sea_level:      i32,
+    pub material_index: usize,
+}
+
+/// Where a carved route ends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CarveTarget {
+    /// The first column at or below `sea_level`, or off the edge of the terrain.
+    Sea,
+    /// A fixed world (x, z) column.
+    Point(i32, i32),
+}
+
+/// One `place` statement: an instance of an entity at a world position.
+#[derive(Debug, Clone)]
+pub struct ResolvedPlacement {
+    pub entity:   String,
+    /// World voxel of the instance's bottom-centre: the script's world-unit
+    /// position divided by the world voxel size.  With `on`, `y` is a height
+    /// above that surface.
+    pub at:       (i32, i32, i32),
+    /// Quarter turns about the vertical axis.
+    pub rotation: i32,
+    /// `on = terrain` or `on = <Entity>`: rest on that surface.
+    pub on:       Option<String>,
+}
+
+/// Voxel size multiplier of each detail level.  `low` is the entity's own
+/// voxel size; `sketch` draws bounding-box proxies at its size.
+#[derive(Debug, Clone, Copy)]
+pub struct DetailScales {
+    pub sketch: f64,
+    pub low:    f64,
+    pub medium: f64,
+    pub high:   f64,
+}
+
+impl Default for DetailScales {
+    fn default() -> Self {
+        Self { sketch: 1.0, low: 1.0, medium: 0.5, high: 0.25 }
+    }
+}
+
+impl DetailScales {
+    pub fn multiplier(&self, level: DetailLevel) -> f64 {
+        match level {
+            DetailLevel::Sketch => self.sketch,
+            DetailLevel::Low    => self.low,
+            DetailLevel::Medium => self.medium,
+            DetailLevel::High   => self.high,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ResolvedScene {
-    pub atoms:     Vec<ResolvedAtom>,
-    pub materials: Vec<ResolvedMaterial>,
-    pub entities:  Vec<ResolvedEntity>,
-    pub prints:    Vec<PrintStmt>,
-    pub refines:   Vec<RefineStmt>,
+    pub atoms:      Vec<ResolvedAtom>,
+    pub materials:  Vec<ResolvedMaterial>,
+    pub entities:   Vec<ResolvedEntity>,
+    pub generators: Vec<GeneratorDecl>,
+    pub carves:     Vec<ResolvedCarve>,
+    pub placements: Vec<ResolvedPlacement>,
+    /// Every `layout` relation, in source order.
+    pub layout:     Vec<RelationStmt>,
+    pub prints:     Vec<PrintStmt>,
+    pub refines:    Vec<RefineStmt>,
+    pub detail:     DetailScales,
+    /// Voxel size the world is drawn at: `base_voxel_size`, refined to the
+    /// finest `medium`/`high` detail printed or refined so that detail adds
+    /// voxels instead of being averaged away.  Every entity grid is
+    /// resampled onto it.
+    pub voxel_size: f64,
+    /// World voxel size from `world { resolve voxel_size = … }`, before
+    /// refining; detail multipliers apply to it.  Defaults to 1.0.
+    pub base_voxel_size: f64,
+}
+
+impl ResolvedScene {
+    /// Detail an entity is drawn at: `refine Entity detail=…`, else its
+    /// `print` detail, else `low`.
+    pub fn detail_of(&self, entity: &str) -> DetailLevel {
+        self.refines.iter().rev()
+            .find(|r| r.path.len() == 1 && r.path[0].name == entity)
+            .map(|r| r.detail)
+            .or_else(|| self.prints.iter().find(|p| p.target.name == entity).and_then(|p| p.detail))
+            .unwrap_or(DetailLevel::Low)
+    }
+
+    /// What is drawn, and in what order: each `print`ed entity once, in
+    /// print order.  Entities that are not printed still shape the world
+    /// (layout, carving, generator surfaces) but are not drawn.
+    pub fn print_order(&self) -> (Vec<&str>, Vec<String>) {
+        let mut order: Vec<&str> = Vec::new();
+        for p in &self.prints {
+            if !order.contains(&p.target.name.as_str()) {
+                order.push(p.target.name.as_str());
+            }
+        }
+        let mut warnings = Vec::new();
+        if order.is_empty() {
+            warnings.push("nothing is printed, so nothing is drawn (add `print <Entity>` for each entity to draw)".to_string());
+        }
+        (order, warnings)
+    }
+
+    /// Detail of one part from `refine Entity.Part detail=…`, if refined.
+    pub fn part_detail(&self, entity: &str, part: &str) -> Option<DetailLevel> {
+        self.refines.iter().rev()
+            .find(|r| r.path.len() == 2 && r.path[0].name == entity && r.path[1].name == part)
+            .map(|r| r.detail)
+    }
 }
 
 pub struct Resolver {
@@ -50,6 +203,14 @@ pub struct Resolver {
     material_index:  HashMap<String, usize>,
     entity_index:    HashMap<String, usize>,
     generator_index: HashMap<String, usize>,
+    carve_index:     HashMap<String, usize>,
+    /// World voxel size, read in pass 1 so positions anywhere in the script
+    /// can be turned into voxels.
+    voxel_size:      f64,
+}
+
+impl Default for Resolver {
+    fn default() -> Self { Self::new() }
 }
 
 impl Resolver {
@@ -60,27 +221,45 @@ impl Resolver {
             material_index:  HashMap::new(),
             entity_index:    HashMap::new(),
             generator_index: HashMap::new(),
+            carve_index:     HashMap::new(),
+            voxel_size:      1.0,
         }
     }
 
     pub fn resolve(mut self, doc: Document) -> (ResolvedScene, Vec<MoxiError>) {
-        // Pass 1 — register all names so forward references work
+        // Pass 1 — register all names so forward references work, and
+        // settle the voxel size before any position is converted to voxels
+        let mut detail = DetailScales::default();
         for item in &doc.items {
             match item {
                 TopLevel::AtomDecl(a)      => self.register_atom(a),
                 TopLevel::MaterialDecl(m)  => self.register_material_name(m),
                 TopLevel::EntityDecl(e)    => self.register_entity_name(e),
                 TopLevel::GeneratorDecl(g) => self.register_generator_name(g),
+                TopLevel::CarveDecl(c)     => self.register_carve_name(c),
+                TopLevel::DetailDecl(d)    => self.resolve_detail(d, &mut detail),
+                TopLevel::WorldDecl(w)     => {
+                    // Checked in pass 2
+                    if let Some(v) = w.resolve.as_ref().and_then(|o| o.voxel_size).filter(|&v| v > 0.0) {
+                        self.voxel_size = v;
+                    }
+                }
                 _ => {}
             }
         }
+        let base_voxel_size = self.voxel_size;
+        self.voxel_size = refined_voxel_size(&doc, base_voxel_size, &detail);
 
         // Pass 2 — resolve bodies
         let mut atoms     = Vec::new();
         let mut materials = Vec::new();
-        let mut entities  = Vec::new();
-        let mut prints    = Vec::new();
-        let mut refines   = Vec::new();
+        let mut entities   = Vec::new();
+        let mut generators = Vec::new();
+        let mut carves     = Vec::new();
+        let mut placements = Vec::new();
+        let mut layout     = Vec::new();
+        let mut prints     = Vec::new();
+        let mut refines    = Vec::new();
 
         for item in doc.items {
             match item {
@@ -97,6 +276,26 @@ impl Resolver {
                         entities.push(ent);
                     }
                 }
+                TopLevel::GeneratorDecl(g) => {
+                    self.check_generator(&g);
+                    generators.push(g);
+                }
+                TopLevel::CarveDecl(c) => {
+                    if let Some(carve) = self.resolve_carve(c) {
+                        carves.push(carve);
+                    }
+                }
+                TopLevel::LayoutDecl(l) => {
+                    for rel in &l.relations {
+                        self.check_layout_relation(rel);
+                    }
+                    layout.extend(l.relations);
+                }
+                TopLevel::PlaceStmt(p) => {
+                    if let Some(placement) = self.resolve_place(p) {
+                        placements.push(placement);
+                    }
+                }
                 TopLevel::PrintStmt(p) => {
                     self.check_entity_ref(&p.target);
                     prints.push(p);
@@ -107,11 +306,67 @@ impl Resolver {
                     }
                     refines.push(r);
                 }
+                TopLevel::WorldDecl(w) => {
+                    if let Some(opts) = &w.resolve {
+                        for prop in &opts.props {
+                            self.errors.push(MoxiError::InvalidValue {
+                                key:      prop.key.clone(),
+                                expected: "only `voxel_size` in a world's `resolve`".to_string(),
+                                span:     prop.span,
+                            });
+                        }
+                        if opts.voxel_size.is_some_and(|v| v <= 0.0) {
+                            self.errors.push(MoxiError::InvalidValue {
+                                key:      "voxel_size".to_string(),
+                                expected: "a voxel size greater than 0".to_string(),
+                                span:     w.span,
+                            });
+                        }
+                    }
+                }
                 _ => {}
             }
         }
 
-        (ResolvedScene { atoms, materials, entities, prints, refines }, self.errors)
+        self.check_generator_cycles(&generators);
+
+        // `refine Entity.Part` names a part of that entity
+        for r in &refines {
+            match r.path.as_slice() {
+                [_] => {}
+                [root, part] => {
+                    let Some(ent) = entities.iter().find(|e| e.name == root.name) else { continue };
+                    if !ent.parts.iter().any(|p| p.name == part.name) {
+                        self.errors.push(MoxiError::UndefinedName { name: part.name.clone(), span: part.span });
+                    }
+                }
+                _ => self.errors.push(MoxiError::InvalidValue {
+                    key:      "refine".to_string(),
+                    expected: "an entity or Entity.Part".to_string(),
+                    span:     r.span,
+                }),
+            }
+        }
+
+        self.errors.extend(check_relations(&layout, layout_fixed_axes, |r| r.subject.name.clone()));
+
+        // An entity is positioned by `place` or by `layout`, not both
+        for rel in &layout {
+            if placements.iter().any(|p| p.entity == rel.subject.name) {
+                self.errors.push(MoxiError::InvalidValue {
+                    key:      rel.subject.name.clone(),
+                    expected: "an entity not also positioned by `place`".to_string(),
+                    span:     rel.subject.span,
+                });
+            }
+        }
+
+        let scene = ResolvedScene {
+            atoms, materials, entities, generators, carves, placements, layout, prints, refines, detail,
+            voxel_size: self.voxel_size,
+            base_voxel_size,
+        };
+        (scene, self.errors)
     }
 
     // ── Pass 1: registration ───────────────────────────────────────────────
@@ -152,12 +407,33 @@ impl Resolver {
         }
     }
 
+    fn register_carve_name(&mut self, c: &CarveDecl) {
+        let idx = self.carve_index.len();
+        if self.carve_index.insert(c.name.name.clone(), idx).is_some() {
+            self.errors.push(MoxiError::DuplicateName {
+                name: c.name.name.clone(), span: c.name.span,
+            });
+        }
+    }
+
     // ── Pass 2: body resolution ────────────────────────────────────────────
 
-    fn resolve_atom(&self, a: AtomDecl) -> ResolvedAtom {
+    fn resolve_atom(&mut self, a: AtomDecl) -> ResolvedAtom {
         let color = self.extract_str_prop(&a.props, "color")
             .unwrap_or_else(|| "white".to_string());
-        ResolvedAtom { name: a.name.name, color }
+        let density = match a.props.iter().find(|p| p.key == "density") {
+            None => 1.0,
+            Some(p) => match expr_f64(&p.value) {
+                Some(d) if d > 0.0 => d,
+                _ => {
+                    self.errors.push(MoxiError::InvalidValue {
+                        key: "density".to_string(), expected: "a number greater than 0".to_string(), span: p.span,
+                    });
+                    1.0
+                }
+            },
+        };
+        ResolvedAtom { name: a.name.name, color, density }
     }
 
     fn resolve_material(&mut self, m: MaterialDecl) -> Option<ResolvedMaterial> {
@@ -214,21 +490,73 @@ impl Resolver {
                 None => None,
             };
 
+            let mirror_of = part.mirror_of.map(|m| {
+                if part.shape.is_some() {
+                    self.errors.push(MoxiError::InvalidValue {
+                        key:      "mirror_of".to_string(),
+                        expected: "a part without a shape of its own".to_string(),
+                        span:     m.source.span,
+                    });
+                }
+                let axis = match &m.axis {
+                    None    => Axis::X,
+                    Some(a) => Axis::parse(&a.name).unwrap_or_else(|| {
+                        self.errors.push(MoxiError::InvalidValue {
+                            key: "axis".to_string(), expected: "x | y | z".to_string(), span: a.span,
+                        });
+                        Axis::X
+                    }),
+                };
+                (m.source, axis)
+            });
+
             parts.push(ResolvedPart {
                 name:           part.name.name,
                 shape:          part.shape,
                 material_index,
-                anchor:         part.anchor.map(|a| a.name),
+                mirror_of:      mirror_of.as_ref().map(|(source, axis)| (source.name.clone(), *axis)),
+                anchor:         part.anchor.map(|a| a.value),
                 attach_to:      part.attach_to,
             });
         }
 
+        // A mirror copies a part that has a shape
+        for part in &parts {
+            let Some((source, _)) = &part.mirror_of else { continue };
+            let span = part_names[&part.name];
+            match parts.iter().find(|p| &p.name == source) {
+                None => self.errors.push(MoxiError::UndefinedName { name: source.clone(), span }),
+                Some(src) if src.shape.is_none() => self.errors.push(MoxiError::InvalidValue {
+                    key:      source.clone(),
+                    expected: "a part with a shape to mirror".to_string(),
+                    span,
+                }),
+                Some(_) => {}
+            }
+        }
+
         // Validate relation names reference known parts
-        for rel in &e.relations {
+        let mut relations = e.relations;
+        for rel in &relations {
             self.check_part_ref(&rel.subject, &part_names);
             self.check_part_ref(&rel.object,  &part_names);
+            self.check_relation_qualifiers(rel);
+        }
+
+        // `attach_to` joins the relations, so it is ordered and checked
+        // against them like any other
+        for part in &parts {
+            let span = part_names[&part.name];
+            if let Some(rel) = self.resolve_attach(part, span, &part_names) {
+                relations.push(rel);
+            }
         }
 
+        let mirrors: Vec<(String, String, Axis)> = parts.iter()
+            .filter_map(|p| p.mirror_of.as_ref().map(|(source, axis)| (p.name.clone(), source.clone(), *axis)))
+            .collect();
+        self.errors.extend(check_relations(&relations, fixed_axes, |r| placed_part(r, &mirrors)));
+
         // Validate constraint names reference known parts
         for con in &e.constraints {
             match &con.expr {
@@ -236,21 +564,942 @@ impl Resolver {
                     self.check_part_ref(&r.subject, &part_names);
                     self.check_part_ref(&r.object,  &part_names);
                 }
-                ConstraintExpr::Bound { name, .. } => {
-                    self.check_part_ref(name, &part_names);
+                ConstraintExpr::Bound { lhs, rhs, .. } => {
+                    self.check_measure(lhs, &e.name.name, &part_names, con.span);
+                    self.check_measure(rhs, &e.name.name, &part_names, con.span);
+                }
+                ConstraintExpr::Rule(rule) if rule.name != "no_overlap" => {
+                    self.errors.push(MoxiError::InvalidValue {
+                        key:      rule.name.clone(),
+                        expected: "a relation, a bound (`Skull.height < 10`) or `no_overlap`".to_string(),
+                        span:     con.span,
+                    });
                 }
+                ConstraintExpr::Rule(_) => {}
             }
         }
 
+        let grounding = self.resolve_grounding(e.resolve.as_ref(), &part_names);
+
         Some(ResolvedEntity {
             name:        e.name.name,
             parts,
-            relations:   e.relations,
+            relations,
             constraints: e.constraints,
             resolve:     e.resolve,
+            grounding,
+        })
+    }
+
+    /// One side of a constraint bound: numbers, `+ - * /`, properties of
+    /// the entity's parts or of the entity itself (`Skull.height`,
+    /// `Skeleton.width`, bare `mass`), and `distance(A, B)`.
+    fn check_measure(&mut self, e: &Expr, entity: &str, part_names: &HashMap<String, Span>, span: Span) {
+        let measure_list = || MEASURES.join(", ");
+        match e {
+            Expr::Int(_) | Expr::Float(_) => {}
+            Expr::BinOp { op: BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div, lhs, rhs } => {
+                self.check_measure(lhs, entity, part_names, span);
+                self.check_measure(rhs, entity, part_names, span);
+            }
+            Expr::Ident(i) if MEASURES.contains(&i.name.as_str()) => {}
+            Expr::Ident(i) if part_names.contains_key(&i.name) || i.name == entity => {
+                self.errors.push(MoxiError::InvalidValue {
+                    key:      i.name.clone(),
+                    expected: format!("a property of it, e.g. `{}.height` ({})", i.name, measure_list()),
+                    span:     i.span,
+                });
+            }
+            Expr::Ident(i) => self.errors.push(MoxiError::UndefinedName { name: i.name.clone(), span: i.span }),
+            Expr::Property { owner, name } => {
+                if owner.name != entity {
+                    self.check_part_ref(owner, part_names);
+                }
+                if !MEASURES.contains(&name.name.as_str()) {
+                    self.errors.push(MoxiError::InvalidValue {
+                        key: name.name.clone(), expected: format!("a property: {}", measure_list()), span: name.span,
+                    });
+                }
+            }
+            Expr::Call { name, positional, args } if name == "distance" => {
+                let owners: Vec<&Ident> = positional.iter()
+                    .filter_map(|p| match p { Expr::Ident(i) => Some(i), _ => None })
+                    .collect();
+                if owners.len() != 2 || positional.len() != 2 || !args.is_empty() {
+                    self.errors.push(MoxiError::InvalidValue {
+                        key: "distance".to_string(), expected: "distance(PartA, PartB)".to_string(), span,
+                    });
+                }
+                for owner in owners.into_iter().filter(|o| o.name != entity) {
+                    self.check_part_ref(owner, part_names);
+                }
+            }
+            Expr::Call { name, .. } => self.errors.push(MoxiError::UndefinedName { name: name.clone(), span }),
+            _ => self.errors.push(MoxiError::InvalidValue {
+                key:      "constraint".to_string(),
+                expected: "numbers, properties, `distance(A, B)` and + - * /".to_string(),
+                span,
+            }),
+        }
+    }
+
+    /// `resolve floating = …`, `connectivity = 6 | 26`, `ground = Part`.
+    fn resolve_grounding(&mut self, opts: Option<&ResolveOpts>, part_names: &HashMap<String, Span>) -> Grounding {
+        let mut grounding = Grounding::default();
+        for prop in opts.map(|o| o.props.as_slice()).unwrap_or_default() {
+            let ident = match &prop.value { Expr::Ident(i) => Some(i), _ => None };
+            let ok = match prop.key.as_str() {
+                "floating" => {
+                    let policy = match (&prop.value, ident.map(|i| i.name.as_str())) {
+                        (_, Some("warn"))   => Some(FloatingPolicy::Warn),
+                        (_, Some("error"))  => Some(FloatingPolicy::Error),
+                        (_, Some("remove")) => Some(FloatingPolicy::Remove { max: REMOVE_MAX }),
+                        (_, Some("drop"))   => Some(FloatingPolicy::Drop),
+                        // `remove(max = 20)`
+                        (Expr::Call { name, positional, args }, _) if name == "remove" && positional.is_empty() => {
+                            match args.as_slice() {
+                                [arg] if arg.key == "max" => match arg.value {
+                                    Expr::Int(n) if n >= 1 => Some(FloatingPolicy::Remove { max: n as usize }),
+                                    _ => None,
+                                },
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    };
+                    policy.map(|p| grounding.policy = p).is_some()
+                }
+                "connectivity" => match prop.value {
+                    Expr::Int(6)  => { grounding.diagonal = false; true }
+                    Expr::Int(26) => { grounding.diagonal = true; true }
+                    _             => false,
+                },
+                "ground" => match ident {
+                    Some(part) => {
+                        self.check_part_ref(part, part_names);
+                        grounding.ground = Some(part.name.clone());
+                        true
+                    }
+                    None => false,
+                },
+                _ => {
+                    self.errors.push(MoxiError::InvalidValue {
+                        key:      prop.key.clone(),
+                        expected: "a resolve setting: voxel_size, floating, connectivity or ground".to_string(),
+                        span:     prop.span,
+                    });
+                    continue;
+                }
+            };
+            if !ok {
+                let expected = match prop.key.as_str() {
+                    "floating"     => "warn | error | remove | remove(max = n) | drop, n at least 1",
+                    "connectivity" => "6 or 26",
+                    _              => "a part name",
+                };
+                self.errors.push(MoxiError::InvalidValue {
+                    key: prop.key.clone(), expected: expected.to_string(), span: prop.span,
+                });
+            }
+        }
+        grounding
+    }
+
+    /// `attach_to = top_of(Trunk)` as the relation it stands for, carrying
+    /// the anchor function and the part's `anchor`.  Checks the function,
+    /// its target and arguments, and the anchor.
+    fn resolve_attach(
+        &mut self,
+        part:       &ResolvedPart,
+        span:       Span,
+        part_names: &HashMap<String, Span>,
+    ) -> Option<RelationStmt> {
+        if let Some(anchor) = &part.anchor {
+            let ok = match anchor {
+                Expr::Ident(i) => matches!(i.name.as_str(), "base" | "center" | "top"),
+                Expr::Tuple(items) => items.len() == 3 && items.iter().all(|e| expr_f64(e).is_some()),
+                _ => false,
+            };
+            if !ok {
+                self.errors.push(MoxiError::InvalidValue {
+                    key: "anchor".to_string(), expected: "base | center | top | (x, y, z)".to_string(), span,
+                });
+            } else if part.attach_to.is_none() {
+                self.errors.push(MoxiError::InvalidValue {
+                    key:      "anchor".to_string(),
+                    expected: "a part with `attach_to` (the anchor is the point that lands on it)".to_string(),
+                    span,
+                });
+            }
+        }
+
+        let spec = part.attach_to.as_ref()?;
+        let Some(predicate) = attach_relation(&spec.anchor_fn) else {
+            self.errors.push(MoxiError::UndefinedName { name: spec.anchor_fn.clone(), span: spec.span });
+            return None;
+        };
+        self.check_part_ref(&spec.target, part_names);
+
+        let uv_ok = spec.args.iter().all(|e| matches!(expr_f64(e), Some(t) if (0.0..=1.0).contains(&t)));
+        let wants_uv = spec.anchor_fn == "surface_at";
+        if (wants_uv && (spec.args.len() != 2 || !uv_ok)) || (!wants_uv && !spec.args.is_empty()) {
+            self.errors.push(MoxiError::InvalidValue {
+                key:      spec.anchor_fn.clone(),
+                expected: if wants_uv { "surface_at(Part, u, v) with u and v in 0..1" } else { "just the target part" }.to_string(),
+                span:     spec.span,
+            });
+        }
+
+        let mut args = vec![Prop {
+            key:   "attach".to_string(),
+            value: Expr::Call { name: spec.anchor_fn.clone(), positional: spec.args.clone(), args: Vec::new() },
+            span:  spec.span,
+        }];
+        if let Some(anchor) = &part.anchor {
+            args.push(Prop { key: "anchor".to_string(), value: anchor.clone(), span });
+        }
+        Some(RelationStmt {
+            subject:    Ident { name: part.name.clone(), span },
+            predicate,
+            object:     spec.target.clone(),
+            qualifiers: Vec::new(),
+            args,
+            span:       spec.span,
+        })
+    }
+
+    fn resolve_carve(&mut self, c: CarveDecl) -> Option<ResolvedCarve> {
+        let name = c.name.name.clone();
+        let prop = |key: &str| c.props.iter().find(|p| p.key == key);
+
+        let from = match prop("from") {
+            Some(p) => self.expect_xz(p)?,
+            None => {
+                self.errors.push(MoxiError::MissingProperty {
+                    key: "from".to_string(), owner: name, span: c.name.span,
+                });
+                return None;
+            }
+        };
+
+        let to = match prop("to") {
+            Some(p) => match &p.value {
+                Expr::Ident(i) if i.name == "sea" => CarveTarget::Sea,
+                _ => {
+                    let (x, z) = self.expect_xz(p)?;
+                    CarveTarget::Point(x, z)
+                }
+            },
+            None if c.kind == CarveKind::River => CarveTarget::Sea,
+            None => {
+                self.errors.push(MoxiError::MissingProperty {
+                    key: "to".to_string(), owner: name, span: c.name.span,
+                });
+                return None;
+            }
+        };
+
+        let material_index = match prop("material") {
+            Some(p) => match &p.value {
+                Expr::Ident(mat) => match self.material_index.get(&mat.name).copied() {
+                    Some(idx) => idx,
+                    None => {
+                        self.errors.push(MoxiError::UndefinedMaterial {
+                            name: mat.name.clone(), span: mat.span,
+                        });
+                        return None;
+                    }
+                },
+                _ => {
+                    self.errors.push(MoxiError::InvalidValue {
+                        key: "material".to_string(),
+                        expected: "a material name".to_string(),
+                        span: p.span,
+                    });
+                    return None;
+                }
+            },
+            None => {
+                self.errors.push(MoxiError::MissingProperty {
+                    key: "material".to_string(), owner: name, span: c.name.span,
+                });
+                return None;
+            }
+        };
+
+        let (default_width, default_depth) = match c.kind {
+            CarveKind::River => (3.0, 2),
+            CarveKind::Path  => (2.0, 1),
+        };
+        let width     = self.extract_f64_prop(&c.props, "width").unwrap_or(default_width);
+        let depth     = self.extract_f64_prop(&c.props, "depth").unwrap_or(default_depth as f64);
+        let sea_level = self.extract_f64_prop(&c.props, "sea_level").map(|s| s as i32).unwrap_or(0);
+
+        // A channel needs at least one column across and one voxel down
+        let mut valid = true;
+        for (key, ok, expected) in [
+            ("width", width > 0.0,  "a number greater than 0"),
+            ("depth", depth >= 1.0, "a number of voxels, at least 1"),
+        ] {
+            if !ok {
+                let span = prop(key).map(|p| p.span).unwrap_or(c.span);
+                self.errors.push(MoxiError::InvalidValue {
+                    key: key.to_string(), expected: expected.to_string(), span,
+                });
+                valid = false;
+            }
+        }
+        if !valid {
+            return None;
+        }
+
+        Some(ResolvedCarve {
+            name, kind: c.kind, from, to, width, depth: depth as i32, sea_level, material_index,
         })
     }
 
+    /// Layout relations are between entities; `across` may also span a
+    /// river or path, and `on` may rest on `terrain`.
+    fn check_layout_relation(&mut self, rel: &RelationStmt) {
+        self.check_entity_ref(&rel.subject);
+
+        let obj = &rel.object;
+        let ok = self.entity_index.contains_key(&obj.name)
+            || (rel.predicate == RelationKind::Across && self.carve_index.contains_key(&obj.name))
+            || (rel.predicate == RelationKind::On && obj.name == "terrain");
+        if !ok && self.carve_index.contains_key(&obj.name) {
+            self.errors.push(MoxiError::InvalidValue {
+                key:      obj.name.clone(),
+                expected: "an entity (only `across` takes a river or path)".to_string(),
+                span:     obj.span,
+            });
+        } else if !ok {
+            self.errors.push(MoxiError::UndefinedName { name: obj.name.clone(), span: obj.span });
+        }
+
+        if rel.predicate == RelationKind::Across && self.carve_index.contains_key(&obj.name) {
+            if let Some(word) = rel.qualifiers.iter().find(|q| q.name.starts_with("flush_")) {
+                self.errors.push(MoxiError::InvalidValue {
+                    key:      word.name.clone(),
+                    expected: "no flush alignment across a river or path (it is centred on the route)".to_string(),
+                    span:     word.span,
+                });
+            }
+        }
+        self.check_relation_qualifiers(rel);
+    }
+
+    /// Relation qualifiers: `gap`/`overlap` along the axis the relation
+    /// pushes along, `centered`/`flush_*` on an axis it doesn't, and
+    /// `offset = (x, y, z)` on any.
+    fn check_relation_qualifiers(&mut self, rel: &RelationStmt) {
+        let push = push_direction(&rel.predicate).map(|(axis, _)| axis);
+        let predicate = relation_word(&rel.predicate);
+
+        let mut spacing: Option<&str> = None;
+        for arg in &rel.args {
+            match arg.key.as_str() {
+                "gap" | "overlap" => {
+                    if !matches!(expr_f64(&arg.value), Some(g) if g >= 0.0) {
+                        self.errors.push(MoxiError::InvalidValue {
+                            key:      arg.key.clone(),
+                            expected: "a non-negative length".to_string(),
+                            span:     arg.span,
+                        });
+                    } else if push.is_none() {
+                        self.errors.push(MoxiError::InvalidValue {
+                            key:      arg.key.clone(),
+                            expected: format!("a relation that pushes its subject away (not {predicate})"),
+                            span:     arg.span,
+                        });
+                    } else if let Some(other) = spacing {
+                        self.errors.push(MoxiError::InvalidValue {
+                            key:      arg.key.clone(),
+                            expected: format!("one of gap/overlap (`{other}` is already given)"),
+                            span:     arg.span,
+                        });
+                    }
+                    spacing = Some(&arg.key);
+                }
+                "offset" => {
+                    let ok = matches!(&arg.value, Expr::Tuple(items)
+                        if items.len() == 3 && items.iter().all(|e| expr_f64(e).is_some()));
+                    if !ok {
+                        self.errors.push(MoxiError::InvalidValue {
+                            key:      arg.key.clone(),
+                            expected: "(x, y, z)".to_string(),
+                            span:     arg.span,
+                        });
+                    }
+                }
+                "axis" => {
+                    if rel.predicate != RelationKind::SymmetricAcross {
+                        self.errors.push(MoxiError::InvalidValue {
+                            key:      arg.key.clone(),
+                            expected: "symmetric_across (only mirrors take an axis)".to_string(),
+                            span:     arg.span,
+                        });
+                    } else {
+                        self.check_keyword(arg, &["x", "y", "z"]);
+                    }
+                }
+                other => self.errors.push(MoxiError::UnexpectedToken {
+                    got:      other.to_string(),
+                    expected: "gap / overlap / offset / axis".to_string(),
+                    span:     arg.span,
+                }),
+            }
+        }
+
+        // Alignment words, by the axis they line up
+        let mut aligned: [Option<&str>; 3] = [None; 3];
+        let mut resting: Option<&str> = None;
+        for word in &rel.qualifiers {
+            if matches!(word.name.as_str(), "contact" | "bbox") {
+                if push.is_none() {
+                    self.errors.push(MoxiError::InvalidValue {
+                        key:      word.name.clone(),
+                        expected: format!("a relation that pushes its subject away (not {predicate})"),
+                        span:     word.span,
+                    });
+                } else if resting.is_some_and(|other| other != word.name) {
+                    self.errors.push(MoxiError::InvalidValue {
+                        key:      word.name.clone(),
+                        expected: "one of contact/bbox".to_string(),
+                        span:     word.span,
+                    });
+                }
+                resting = Some(&word.name);
+                continue;
+            }
+            let axes: &[usize] = match word.name.as_str() {
+                "flush_left" | "flush_right" => &[0],
+                "flush_front" | "flush_back" => &[2],
+                "centered"                   => &[0, 2],
+                other => {
+                    self.errors.push(MoxiError::UnexpectedToken {
+                        got:      other.to_string(),
+                        expected: "centered / flush_left / flush_right / flush_front / flush_back / contact / bbox".to_string(),
+                        span:     word.span,
+                    });
+                    continue;
+                }
+            };
+            if rel.predicate == RelationKind::SymmetricAcross {
+                self.errors.push(MoxiError::InvalidValue {
+                    key:      word.name.clone(),
+                    expected: "no alignment on symmetric_across (it mirrors)".to_string(),
+                    span:     word.span,
+                });
+                continue;
+            }
+            for &axis in axes {
+                if push == Some(axis) {
+                    if word.name != "centered" {
+                        self.errors.push(MoxiError::InvalidValue {
+                            key:      word.name.clone(),
+                            expected: format!("an axis {predicate} does not push along"),
+                            span:     word.span,
+                        });
+                    }
+                } else if let Some(other) = aligned[axis] {
+                    if other != word.name {
+                        self.errors.push(MoxiError::InvalidValue {
+                            key:      word.name.clone(),
+                            expected: format!("one alignment per axis (`{other}` is already given)"),
+                            span:     word.span,
+                        });
+                    }
+                } else {
+                    aligned[axis] = Some(&word.name);
+                }
+            }
+        }
+    }
+
+    /// `detail { medium = 0.5, … }` overrides the default multipliers.
+    fn resolve_detail(&mut self, d: &DetailDecl, scales: &mut DetailScales) {
+        for prop in &d.props {
+            let slot = match prop.key.as_str() {
+                "sketch" => &mut scales.sketch,
+                "low"    => &mut scales.low,
+                "medium" => &mut scales.medium,
+                "high"   => &mut scales.high,
+                other => {
+                    self.errors.push(MoxiError::UnexpectedToken {
+                        got:      other.to_string(),
+                        expected: "sketch/low/medium/high".to_string(),
+                        span:     prop.span,
+                    });
+                    continue;
+                }
+            };
+            match expr_f64(&prop.value) {
+                Some(m) if m > 0.0 => *slot = m,
+                _ => self.errors.push(MoxiError::InvalidValue {
+                    key:      prop.key.clone(),
+                    expected: "a positive voxel size multiplier".to_string(),
+                    span:     prop.span,
+                }),
+            }
+        }
+    }
+
+    fn resolve_place(&mut self, p: PlaceStmt) -> Option<ResolvedPlacement> {
+        self.check_entity_ref(&p.target);
+
+        let num = |e: &Expr| expr_f64(e).map(|_| voxels(e, self.voxel_size));
+        let at = match &p.at {
+            Expr::Tuple(items) => match items.as_slice() {
+                [x, y, z] => match (num(x), num(y), num(z)) {
+                    (Some(x), Some(y), Some(z)) => Some((x, y, z)),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        };
+        if at.is_none() {
+            self.errors.push(MoxiError::InvalidValue {
+                key: "at".to_string(), expected: "an (x, y, z) position".to_string(), span: p.span,
+            });
+        }
+
+        let mut rotation = 0;
+        let mut on = None;
+        for prop in &p.props {
+            match prop.key.as_str() {
+                "rotate" => match &prop.value {
+                    Expr::Int(deg) if deg % 90 == 0 => rotation = (*deg / 90) as i32,
+                    _ => self.errors.push(MoxiError::InvalidValue {
+                        key:      prop.key.clone(),
+                        expected: "a multiple of 90 degrees".to_string(),
+                        span:     prop.span,
+                    }),
+                },
+                "on" => match &prop.value {
+                    Expr::Ident(i) if i.name == "terrain" => on = Some(i.name.clone()),
+                    Expr::Ident(i) => {
+                        self.check_entity_ref(i);
+                        on = Some(i.name.clone());
+                    }
+                    _ => self.errors.push(MoxiError::InvalidValue {
+                        key:      prop.key.clone(),
+                        expected: "terrain or an entity name".to_string(),
+                        span:     prop.span,
+                    }),
+                },
+                other => self.errors.push(MoxiError::UnexpectedToken {
+                    got:      other.to_string(),
+                    expected: "'rotate' or 'on'".to_string(),
+                    span:     prop.span,
+                }),
+            }
+        }
+
+        Some(ResolvedPlacement { entity: p.target.name, at: at?, rotation, on })
+    }
+
+    /// Generator bodies stay as AST; we only check the names they reference.
+    fn check_generator(&mut self, g: &GeneratorDecl) {
+        let inside = g.props.iter().any(|p| p.key == "inside");
+        let faces  = g.props.iter().any(|p| p.key == "surface" && matches!(
+            &p.value, Expr::Ident(i) if i.name != "top"
+        ));
+        let vein = g.props.iter().any(|p| p.key == "mode" && match &p.value {
+            Expr::Call { name, .. } => name == "vein",
+            Expr::Ident(i)          => i.name == "vein",
+            _                       => false,
+        });
+
+        // Veins grow an atom through the host; everything else places entities
+        for target in &g.scatter_targets {
+            if vein {
+                if !self.atom_index.contains_key(&target.name) && !self.material_index.contains_key(&target.name) {
+                    self.errors.push(MoxiError::UndefinedAtom {
+                        name: target.name.clone(), span: target.span,
+                    });
+                }
+            } else {
+                self.check_entity_ref(target);
+            }
+        }
+        if vein && !inside {
+            self.errors.push(MoxiError::MissingProperty {
+                key: "inside".to_string(), owner: g.name.name.clone(), span: g.name.span,
+            });
+        }
+
+        for prop in &g.props {
+            match prop.key.as_str() {
+                "weights" => self.check_weights(prop, g.scatter_targets.len()),
+                "rotate"  => {
+                    let ok = match &prop.value {
+                        Expr::Int(d)   => d % 90 == 0,
+                        Expr::Ident(i) => i.name == "none" || i.name == "random_y90",
+                        _              => false,
+                    };
+                    if !ok {
+                        self.errors.push(MoxiError::InvalidValue {
+                            key:      prop.key.clone(),
+                            expected: "none | random_y90 | a multiple of 90".to_string(),
+                            span:     prop.span,
+                        });
+                    }
+                }
+                "scale"   => self.check_range(prop, true),
+                "radius"  => self.check_range(prop, false),
+                "around"  => self.check_around(&prop.value, prop.span),
+                "density" if !matches!(expr_f64(&prop.value), Some(d) if d > 0.0) => {
+                    self.errors.push(MoxiError::InvalidValue {
+                        key:      prop.key.clone(),
+                        expected: "a positive number of instances per square voxel".to_string(),
+                        span:     prop.span,
+                    });
+                }
+                "mode"    => self.check_mode(prop, inside, faces),
+                "inside"  => match &prop.value {
+                    Expr::Ident(host) => self.check_entity_ref(host),
+                    _ => self.errors.push(MoxiError::InvalidValue {
+                        key:      prop.key.clone(),
+                        expected: "an entity name".to_string(),
+                        span:     prop.span,
+                    }),
+                },
+                "jitter"  => {
+                    let ok = matches!(expr_f64(&prop.value), Some(j) if (0.0..1.0).contains(&j));
+                    if !ok {
+                        self.errors.push(MoxiError::InvalidValue {
+                            key:      prop.key.clone(),
+                            expected: "a fraction between 0 and 1".to_string(),
+                            span:     prop.span,
+                        });
+                    }
+                }
+                "avoid"   => self.check_avoid(&prop.value, prop.span),
+                "overlap" => self.check_keyword(prop, &["allow", "deny"]),
+                "surface" => self.check_keyword(prop, &["top", "side", "bottom", "any"]),
+                "align"   => self.check_keyword(prop, &["normal", "up"]),
+                "over"    => self.check_over(&prop.value, prop.span),
+                "where"   => self.check_condition(&prop.value, prop.span, inside),
+                _ => {}
+            }
+            self.check_generator_expr(&prop.value);
+        }
+    }
+
+    /// `weights` lists one non-negative number per scatter variant, not all
+    /// zero — a pool with nothing to pick would fall through to its last entry.
+    fn check_weights(&mut self, prop: &Prop, variants: usize) {
+        let ok = match &prop.value {
+            Expr::List(items) => {
+                let weights: Vec<Option<f64>> = items.iter().map(expr_f64).collect();
+                items.len() == variants
+                    && weights.iter().all(|w| matches!(w, Some(v) if *v >= 0.0))
+                    && weights.iter().flatten().sum::<f64>() > 0.0
+            }
+            _ => false,
+        };
+        if !ok {
+            self.errors.push(MoxiError::InvalidValue {
+                key:      prop.key.clone(),
+                expected: format!("a list of {variants} non-negative numbers, one per variant, not all 0"),
+                span:     prop.span,
+            });
+        }
+    }
+
+    /// A number, or a range `lo..hi` with `lo <= hi`; positive, or with
+    /// `positive = false` non-negative.
+    fn check_range(&mut self, prop: &Prop, positive: bool) {
+        let in_domain = |v: f64| if positive { v > 0.0 } else { v >= 0.0 };
+        let ok = match &prop.value {
+            Expr::Range { lo, hi } => matches!(
+                (expr_f64(lo), expr_f64(hi)),
+                (Some(l), Some(h)) if in_domain(l) && l <= h
+            ),
+            other => matches!(expr_f64(other), Some(v) if in_domain(v)),
+        };
+        if !ok {
+            let sign = if positive { "positive" } else { "non-negative" };
+            self.errors.push(MoxiError::InvalidValue {
+                key:      prop.key.clone(),
+                expected: format!("a {sign} number or a range lo..hi"),
+                span:     prop.span,
+            });
+        }
+    }
+
+    /// `around` names one generator or a list of them.
+    fn check_around(&mut self, value: &Expr, span: Span) {
+        match value {
+            Expr::List(items) => {
+                for item in items { self.check_around(item, span); }
+            }
+            Expr::Ident(name) if !self.generator_index.contains_key(&name.name) => {
+                self.errors.push(MoxiError::UndefinedName {
+                    name: name.name.clone(), span: name.span,
+                });
+            }
+            Expr::Ident(_) => {}
+            _ => {
+                self.errors.push(MoxiError::InvalidValue {
+                    key:      "around".to_string(),
+                    expected: "a generator name or a list of them".to_string(),
+                    span,
+                });
+            }
+        }
+    }
+
+    /// Child generators (`around = Parent`) run after their parents, so the
+    /// `around` links must not loop.  Reports each cycle once.
+    fn check_generator_cycles(&mut self, generators: &[GeneratorDecl]) {
+        let parents: Vec<Vec<usize>> = generators.iter()
+            .map(|g| around_names(g).iter()
+                .filter_map(|n| generators.iter().position(|p| &p.name.name == n))
+                .collect())
+            .collect();
+
+        // 0 = unvisited, 1 = on the current path, 2 = done
+        let mut state = vec![0u8; generators.len()];
+        for start in 0..generators.len() {
+            let mut path = Vec::new();
+            self.visit_generator(start, &parents, &mut state, &mut path, generators);
+        }
+    }
+
+    fn visit_generator(
+        &mut self,
+        i:          usize,
+        parents:    &[Vec<usize>],
+        state:      &mut [u8],
+        path:       &mut Vec<usize>,
+        generators: &[GeneratorDecl],
+    ) {
+        match state[i] {
+            2 => return,
+            1 => {
+                let from = path.iter().position(|&p| p == i).unwrap_or(0);
+                let mut cycle: Vec<String> = path[from..].iter()
+                    .map(|&p| generators[p].name.name.clone())
+                    .collect();
+                cycle.push(generators[i].name.name.clone());
+                self.errors.push(MoxiError::DependencyCycle { cycle, span: generators[i].name.span });
+                return;
+            }
+            _ => {}
+        }
+        state[i] = 1;
+        path.push(i);
+        for &p in &parents[i] {
+            self.visit_generator(p, parents, state, path, generators);
+        }
+        path.pop();
+        state[i] = 2;
+    }
+
+    /// `mode` is a scatter strategy, optionally called with its own arguments.
+    /// Generators scattering `inside` a volume, or on `surface = side |
+    /// bottom | any` faces, have their own sets of modes.
+    fn check_mode(&mut self, prop: &Prop, inside: bool, faces: bool) {
+        let (name, args) = match &prop.value {
+            Expr::Ident(i)                => (i.name.as_str(), &[][..]),
+            Expr::Call { name, args, .. } => (name.as_str(), &args[..]),
+            _                             => ("", &[][..]),
+        };
+        let columns = !inside && !faces;
+        let allowed: &[&str] = match name {
+            "uniform"                  => &[],
+            "poisson"                  => &["radius"],
+            "cluster" if columns       => &["radius", "per_cluster"],
+            "grid" | "hex" if columns  => &["spacing", "jitter"],
+            "vein" if inside           => &["length", "branch"],
+            _ => {
+                let expected = if inside {
+                    "uniform | poisson | vein(length, branch) inside a volume"
+                } else if faces {
+                    "uniform | poisson on side, bottom or any faces"
+                } else {
+                    "uniform | poisson | cluster(radius, per_cluster) | grid(spacing, jitter) | hex(spacing, jitter)"
+                };
+                self.errors.push(MoxiError::InvalidValue {
+                    key:      prop.key.clone(),
+                    expected: expected.to_string(),
+                    span:     prop.span,
+                });
+                return;
+            }
+        };
+        for arg in args {
+            let ok = allowed.contains(&arg.key.as_str())
+                && matches!(expr_f64(&arg.value), Some(v) if v >= 0.0);
+            if !ok {
+                self.errors.push(MoxiError::InvalidValue {
+                    key:      format!("{name}({})", arg.key),
+                    expected: format!("a non-negative number for one of: {}", allowed.join(", ")),
+                    span:     prop.span,
+                });
+            }
+        }
+    }
+
+    /// `over` names entities, or `terrain` / `world`.
+    fn check_over(&mut self, value: &Expr, span: Span) {
+        match value {
+            Expr::List(items) => {
+                for item in items { self.check_over(item, span); }
+            }
+            Expr::Ident(name) => {
+                let known = name.name == "terrain"
+                    || name.name == "world"
+                    || self.entity_index.contains_key(&name.name);
+                if !known {
+                    self.errors.push(MoxiError::UndefinedName {
+                        name: name.name.clone(), span: name.span,
+                    });
+                }
+            }
+            _ => {
+                self.errors.push(MoxiError::InvalidValue {
+                    key: "over".to_string(),
+                    expected: "an entity name, a list of names, terrain or world".to_string(),
+                    span,
+                });
+            }
+        }
+    }
+
+    /// `avoid` may name atoms, materials, entities or other generators.
+    fn check_avoid(&mut self, value: &Expr, span: Span) {
+        match value {
+            Expr::List(items) => {
+                for item in items { self.check_avoid(item, span); }
+            }
+            Expr::Ident(name) => {
+                let known = self.atom_index.contains_key(&name.name)
+                    || self.material_index.contains_key(&name.name)
+                    || self.entity_index.contains_key(&name.name)
+                    || self.generator_index.contains_key(&name.name);
+                if !known {
+                    self.errors.push(MoxiError::UndefinedName {
+                        name: name.name.clone(), span: name.span,
+                    });
+                }
+            }
+            Expr::BinOp { .. } | Expr::Not(_) | Expr::Call { .. } | Expr::Tuple(_)
+            | Expr::Range { .. } => {
+                self.errors.push(MoxiError::InvalidValue {
+                    key: "avoid".to_string(),
+                    expected: "a name or a list of names".to_string(),
+                    span,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Every name in a `where` condition must be a known variable, an atom or
+    /// a material (for `surface_material == Sand`), and every call a known
+    /// function.  `material`, `exposed` and `adjacent(…)` only exist for
+    /// generators scattering `inside` a volume.
+    fn check_condition(&mut self, expr: &Expr, span: Span, inside: bool) {
+        const VARIABLES: &[&str] = &[
+            "elevation", "height", "sea_level", "depth", "x", "z",
+            "slope", "aspect", "curvature",
+            "distance_to_water", "distance_to_edge", "surface_material",
+        ];
+        const FUNCTIONS: &[&str] = &["distance_to", "noise", "random"];
+        const VOLUME_VARIABLES: &[&str] = &["material", "exposed"];
+        const VOLUME_FUNCTIONS: &[&str] = &["adjacent"];
+
+        match expr {
+            Expr::Ident(i) => {
+                let known = VARIABLES.contains(&i.name.as_str())
+                    || (inside && VOLUME_VARIABLES.contains(&i.name.as_str()))
+                    || self.atom_index.contains_key(&i.name)
+                    || self.material_index.contains_key(&i.name);
+                if !known {
+                    self.errors.push(MoxiError::UndefinedName {
+                        name: i.name.clone(), span: i.span,
+                    });
+                }
+            }
+            Expr::Call { name, positional, args } => {
+                let known = FUNCTIONS.contains(&name.as_str())
+                    || (inside && VOLUME_FUNCTIONS.contains(&name.as_str()));
+                if !known {
+                    self.errors.push(MoxiError::UndefinedName {
+                        name: name.clone(), span,
+                    });
+                }
+                // `adjacent(Water)` / `adjacent(air)` — atom, material or air
+                if name == "adjacent" {
+                    for arg in positional {
+                        if let Expr::Ident(i) = arg {
+                            let ok = i.name == "air"
+                                || self.atom_index.contains_key(&i.name)
+                                || self.material_index.contains_key(&i.name);
+                            if !ok {
+                                self.errors.push(MoxiError::UndefinedName {
+                                    name: i.name.clone(), span: i.span,
+                                });
+                            }
+                        }
+                    }
+                }
+                // `distance_to(Main)` targets are checked by check_generator_expr
+                for arg in args { self.check_condition(&arg.value, span, inside); }
+            }
+            Expr::BinOp { lhs, rhs, .. } => {
+                self.check_condition(lhs, span, inside);
+                self.check_condition(rhs, span, inside);
+            }
+            Expr::Not(inner) => self.check_condition(inner, span, inside),
+            _ => {}
+        }
+    }
+
+    fn check_generator_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Call { name, positional, args } => {
+                if name == "distance_to" {
+                    for arg in positional {
+                        if let Expr::Ident(target) = arg {
+                            if !self.carve_index.contains_key(&target.name) {
+                                self.errors.push(MoxiError::UndefinedName {
+                                    name: target.name.clone(), span: target.span,
+                                });
+                            }
+                        }
+                    }
+                }
+                for arg in args { self.check_generator_expr(&arg.value); }
+            }
+            Expr::BinOp { lhs, rhs, .. } => {
+                self.check_generator_expr(lhs);
+                self.check_generator_expr(rhs);
+            }
+            Expr::Not(inner) => self.check_generator_expr(inner),
+            Expr::List(items) | Expr::Tuple(items) => {
+                for item in items { self.check_generator_expr(item); }
+            }
+            _ => {}
+        }
+    }
+
+    /// A property whose value must be one of a fixed set of bare words.
+    fn check_keyword(&mut self, prop: &Prop, allowed: &[&str]) {
+        let ok = matches!(&prop.value, Expr::Ident(i) if allowed.contains(&i.name.as_str()));
+        if !ok {
+            self.errors.push(MoxiError::InvalidValue {
+                key:      prop.key.clone(),
+                expected: allowed.join(" | "),
+                span:     prop.span,
+            });
+        }
+    }
+
     fn check_entity_ref(&mut self, ident: &Ident) {
         if !self.entity_index.contains_key(&ident.name) {
             self.errors.push(MoxiError::UndefinedName {
@@ -273,6 +1522,30 @@ impl Resolver {
         props.iter().find(|p| p.key == key).map(|p| self.expr_to_str(&p.value))
     }
 
+    fn extract_f64_prop(&self, props: &[Prop], key: &str) -> Option<f64> {
+        props.iter().find(|p| p.key == key).and_then(|p| match &p.value {
+            Expr::Int(n)   => Some(*n as f64),
+            Expr::Float(f) => Some(*f),
+            _              => None,
+        })
+    }
+
+    /// Read an `(x, z)` tuple of world positions, in voxels.
+    fn expect_xz(&mut self, prop: &Prop) -> Option<(i32, i32)> {
+        let num = |e: &Expr| expr_f64(e).map(|_| voxels(e, self.voxel_size));
+        if let Expr::Tuple(items) = &prop.value {
+            if let [x, z] = items.as_slice() {
+                if let (Some(x), Some(z)) = (num(x), num(z)) {
+                    return Some((x, z));
+                }
+            }
+        }
+        self.errors.push(MoxiError::InvalidValue {
+            key: prop.key.clone(), expected: "an (x, z) pair".to_string(), span: prop.span,
+        });
+        None
+    }
+
     fn expr_to_str(&self, expr: &Expr) -> String {
         match expr {
             Expr::Ident(i) => i.name.clone(),
@@ -282,4 +1555,249 @@ impl Resolver {
             _              => "<complex>".to_string(),
         }
     }
-}
\ No newline at end of file
+}
+
+/// The keyword a relation is written with, for messages.
+fn relation_word(kind: &RelationKind) -> &'static str {
+    match kind {
+        RelationKind::Inside          => "inside",
+        RelationKind::Outside         => "outside",
+        RelationKind::AdjacentTo      => "adjacent_to",
+        RelationKind::Above           => "above",
+        RelationKind::Below           => "below",
+        RelationKind::LeftOf          => "left_of",
+        RelationKind::RightOf         => "right_of",
+        RelationKind::InFrontOf       => "in_front_of",
+        RelationKind::Behind          => "behind",
+        RelationKind::SymmetricAcross => "symmetric_across",
+        RelationKind::AttachedTo      => "attached_to",
+        RelationKind::Touch           => "touch",
+        RelationKind::Surrounds       => "surrounds",
+        RelationKind::On              => "on",
+        RelationKind::Across          => "across",
+    }
+}
+
+/// A literal number.
+fn expr_f64(e: &Expr) -> Option<f64> {
+    match e {
+        Expr::Int(n)   => Some(*n as f64),
+        Expr::Float(f) => Some(*f),
+        _              => None,
+    }
+}
+
+/// The world voxel size once detail is applied: the finest voxel size any
+/// entity or part is drawn at through a `medium`/`high`-style level (one
+/// finer than `low`), or `base` if nothing is.  Follows the same choice of
+/// level as `ResolvedScene::detail_of` and `part_detail`.
+fn refined_voxel_size(doc: &Document, base: f64, detail: &DetailScales) -> f64 {
+    let mut prints  = Vec::new();
+    let mut refines = Vec::new();
+    let mut sizes   = HashMap::new();
+    for item in &doc.items {
+        match item {
+            TopLevel::PrintStmt(p)  => prints.push(p),
+            TopLevel::RefineStmt(r) => refines.push(r),
+            TopLevel::EntityDecl(e) => {
+                let vs = e.resolve.as_ref().and_then(|o| o.voxel_size).filter(|&v| v > 0.0);
+                sizes.insert(e.name.name.as_str(), vs.unwrap_or(base));
+            }
+            _ => {}
+        }
+    }
+
+    let mut finest = base;
+    for (&name, &size) in &sizes {
+        let refined = |path_len: usize| refines.iter().rev()
+            .filter(move |r| r.path.len() == path_len && r.path[0].name == name);
+        let entity_level = refined(1).map(|r| r.detail).next()
+            .or_else(|| prints.iter().find(|p| p.target.name == name).and_then(|p| p.detail));
+
+        // Each part's last `refine Entity.Part`, plus the entity's own level
+        let mut parts: Vec<&str> = Vec::new();
+        let mut levels: Vec<DetailLevel> = entity_level.into_iter().collect();
+        for r in refined(2) {
+            if !parts.contains(&r.path[1].name.as_str()) {
+                parts.push(r.path[1].name.as_str());
+                levels.push(r.detail);
+            }
+        }
+        for level in levels {
+            let m = detail.multiplier(level);
+            if m < detail.low {
+                finest = finest.min(size * m);
+            }
+        }
+    }
+    finest
+}
+
+/// The generators named by `around = …`, for the cycle check here and for
+/// the generator pass's run order.
+pub fn around_names(g: &GeneratorDecl) -> Vec<String> {
+    match g.props.iter().find(|p| p.key == "around").map(|p| &p.value) {
+        Some(Expr::List(items)) => items.iter()
+            .filter_map(|e| if let Expr::Ident(i) = e { Some(i.name.clone()) } else { None })
+            .collect(),
+        Some(Expr::Ident(i)) => vec![i.name.clone()],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::relation_resolver::Qualifiers;
+
+    fn resolve(src: &str) -> ResolvedScene {
+        let (tokens, lex_errors) = Lexer::new(src).tokenize();
+        let (doc, parse_errors) = Parser::new(tokens).parse();
+        let (scene, errors) = Resolver::new().resolve(doc);
+        assert!(lex_errors.is_empty() && parse_errors.is_empty() && errors.is_empty(),
+            "{lex_errors:?} {parse_errors:?} {errors:?}");
+        scene
+    }
+
+    /// Resolver errors for a script that lexes and parses cleanly.
+    fn errors(src: &str) -> Vec<MoxiError> {
+        let (tokens, _) = Lexer::new(src).tokenize();
+        let (doc, parse_errors) = Parser::new(tokens).parse();
+        assert!(parse_errors.is_empty(), "{parse_errors:?}");
+        Resolver::new().resolve(doc).1
+    }
+
+    #[test]
+    fn positions_convert_to_voxels_like_relation_lengths() {
+        // The world voxel size comes last; it still applies to everything
+        let scene = resolve("
+            atom A { color = red }
+            material M { color = red, voxel_atom = A }
+            entity Hut   { part P { shape = box(width=1, height=1, depth=1), material = M } }
+            entity Tower { part P { shape = box(width=1, height=1, depth=1), material = M } }
+            river Creek { from = (3, -2), to = (4.5, 1), material = M }
+            place Hut at (4, 1, -3)
+            layout { Tower right_of Hut gap=4 offset=(0, 1, -3) }
+            world W { resolve voxel_size = 0.5 }
+        ");
+        assert_eq!(scene.voxel_size, 0.5);
+
+        let q = Qualifiers::of(&scene.layout[0], scene.voxel_size);
+        assert_eq!((q.gap, q.offset), (8, (0, 2, -6)));
+        assert_eq!(scene.placements[0].at, (8, 2, -6));
+        assert_eq!(scene.carves[0].from, (6, -4));
+        assert_eq!(scene.carves[0].to, CarveTarget::Point(9, 2));
+    }
+
+    #[test]
+    fn generator_properties_are_checked() {
+        let errors = errors("
+            atom A { color = red }
+            material M { color = red, voxel_atom = A }
+            entity Tree { part P { shape = box(width=1, height=1, depth=1), material = M } }
+            generator Forest {
+                scatter [Tree, Tree] rotate = 45 jitter = 2 overlap = maybe density = 0.5 weights = [0, 0]
+            }
+        ");
+        let keys: Vec<&str> = errors.iter()
+            .filter_map(|e| match e { MoxiError::InvalidValue { key, .. } => Some(key.as_str()), _ => None })
+            .collect();
+        assert_eq!(keys, ["rotate", "jitter", "overlap", "weights"]);
+        assert_eq!(errors.len(), 4);
+    }
+
+    #[test]
+    fn carves_need_a_positive_width_and_depth() {
+        let errors = errors("
+            atom A { color = blue }
+            material M { color = blue, voxel_atom = A }
+            river Dry  { from = (0, 0), material = M, width = 0 }
+            path Sunk  { from = (0, 0), to = (4, 4), material = M, width = -2, depth = 0 }
+            river Fine { from = (0, 0), material = M, width = 1, depth = 1 }
+        ");
+        let keys: Vec<&str> = errors.iter()
+            .filter_map(|e| match e { MoxiError::InvalidValue { key, .. } => Some(key.as_str()), _ => None })
+            .collect();
+        assert_eq!(keys, ["width", "width", "depth"]);
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn placements_turn_and_rest_on_surfaces() {
+        let src = "
+            atom A { color = red }
+            material M { color = red, voxel_atom = A }
+            entity Hut { part P { shape = box(width=1, height=1, depth=1), material = M } }
+            entity Hill { part P { shape = box(width=1, height=1, depth=1), material = M } }
+        ";
+        let scene = resolve(&format!("{src}
+            place Hut at (1, 2, 3) rotate = 270 on = Hill
+            translate Hut (-4, 0, 0)
+        "));
+        let [a, b] = &scene.placements[..] else { panic!("{:?}", scene.placements) };
+        assert_eq!((a.at, a.rotation, a.on.as_deref()), ((1, 2, 3), 3, Some("Hill")));
+        assert_eq!((b.at, b.rotation, b.on.as_deref()), ((-4, 0, 0), 0, None));
+
+        let errors = errors(&format!("{src} place Hut at (1, 2) rotate = 45 on = Nowhere"));
+        assert_eq!(errors.len(), 3, "{errors:?}");
+    }
+
+    #[test]
+    fn attach_to_needs_a_known_function_target_and_anchor() {
+        let errors = errors("
+            atom A { color = red }
+            material M { color = red, voxel_atom = A }
+            entity Tree {
+                part Trunk { shape = box(width=1, height=4, depth=1), material = M }
+                part A { shape = sphere(radius=1), material = M, attach_to = side_of(Trunk) }
+                part B { shape = sphere(radius=1), material = M, attach_to = top_of(Nothing) }
+                part C { shape = sphere(radius=1), material = M, attach_to = top_of(Trunk), anchor = sideways }
+                part D { shape = sphere(radius=1), material = M, anchor = base }
+                part E { shape = sphere(radius=1), material = M, attach_to = surface_at(Trunk, 0.5) }
+                part F { shape = sphere(radius=1), material = M, attach_to = surface_at(Trunk, 0.2, 0.8), anchor = (0, -1, 0) }
+            }
+        ");
+        let names: Vec<&str> = errors.iter()
+            .filter_map(|e| match e { MoxiError::UndefinedName { name, .. } => Some(name.as_str()), _ => None })
+            .collect();
+        let keys: Vec<&str> = errors.iter()
+            .filter_map(|e| match e { MoxiError::InvalidValue { key, .. } => Some(key.as_str()), _ => None })
+            .collect();
+        assert_eq!(names, ["side_of", "Nothing"], "{errors:?}");
+        assert_eq!(keys, ["anchor", "anchor", "surface_at"], "{errors:?}");
+        assert_eq!(errors.len(), 5);
+    }
+
+    #[test]
+    fn only_printed_entities_are_drawn_in_print_order() {
+        let scene = resolve("
+            atom A { color = red }
+            material M { color = red, voxel_atom = A }
+            entity Hut   { part P { shape = box(width=1, height=1, depth=1), material = M } }
+            entity Tower { part P { shape = box(width=1, height=1, depth=1), material = M } }
+            entity Wall  { part P { shape = box(width=1, height=1, depth=1), material = M } }
+            print Tower
+            print Hut
+            print Tower
+        ");
+        // Wall is never printed; Tower is drawn once, first
+        let (order, warnings) = scene.print_order();
+        assert_eq!(order, ["Tower", "Hut"]);
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn nothing_printed_warns() {
+        let scene = resolve("
+            atom A { color = red }
+            material M { color = red, voxel_atom = A }
+            entity Hut { part P { shape = box(width=1, height=1, depth=1), material = M } }
+        ");
+        let (order, warnings) = scene.print_order();
+        assert!(order.is_empty());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("nothing is printed"), "{warnings:?}");
+    }
+}
@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+
+use crate::geom::{rotate_point_90, Axis};
+
 /// The flat 3D grid — the floor of the entire pipeline.
 ///
 /// Every cell holds a u16 atom ID.  0 = empty air.
@@ -70,4 +74,191 @@ impl VoxelGrid {
     pub fn dims(&self) -> (u32, u32, u32) {
         (self.width, self.height, self.depth)
     }
-}
\ No newline at end of file
+
+    /// A copy turned `turns` quarter-turns about `axis`.  The two dimensions
+    /// across the axis swap on odd turns.
+    pub fn rotated(&self, axis: Axis, turns: i32) -> VoxelGrid {
+        // Rotate the far corner to find the new extent and where it lands
+        let far = (self.width as i32 - 1, self.height as i32 - 1, self.depth as i32 - 1);
+        let (fx, fy, fz) = rotate_point_90(far.0, far.1, far.2, axis, turns);
+        let (min_x, min_y, min_z) = (fx.min(0), fy.min(0), fz.min(0));
+        let mut out = VoxelGrid::new(
+            fx.unsigned_abs() + 1,
+            fy.unsigned_abs() + 1,
+            fz.unsigned_abs() + 1,
+        );
+
+        // Rotate about the origin, then shift back into positive space
+        for (x, y, z, atom) in self.iter_filled() {
+            let (rx, ry, rz) = rotate_point_90(x as i32, y as i32, z as i32, axis, turns);
+            out.set(rx - min_x, ry - min_y, rz - min_z, atom);
+        }
+        out
+    }
+
+    /// A copy flipped along `axis`: the mirror image across the plane
+    /// through the grid's centre perpendicular to it.
+    pub fn mirrored(&self, axis: Axis) -> VoxelGrid {
+        let mut out = VoxelGrid::new(self.width, self.height, self.depth);
+        for (x, y, z, atom) in self.iter_filled() {
+            let (x, y, z) = match axis {
+                Axis::X => (self.width - 1 - x, y, z),
+                Axis::Y => (x, self.height - 1 - y, z),
+                Axis::Z => (x, y, self.depth - 1 - z),
+            };
+            out.set(x as i32, y as i32, z as i32, atom);
+        }
+        out
+    }
+
+    /// A copy shrunk by `factor` (below 1).  Each output voxel is filled when
+    /// the input voxels falling into it cover at least half of it, with the
+    /// most common of their atoms.
+    pub fn downsampled(&self, factor: f64) -> VoxelGrid {
+        let size = |n: u32| ((n as f64 * factor).ceil() as u32).max(1);
+        let mut out = VoxelGrid::new(size(self.width), size(self.height), size(self.depth));
+
+        let mut counts: HashMap<(i32, i32, i32), HashMap<u16, u32>> = HashMap::new();
+        for (x, y, z, atom) in self.iter_filled() {
+            let cell = |n: u32| (n as f64 * factor) as i32;
+            *counts.entry((cell(x), cell(y), cell(z))).or_default().entry(atom).or_default() += 1;
+        }
+
+        let full = factor.recip().powi(3);
+        for ((x, y, z), atoms) in counts {
+            let total: u32 = atoms.values().sum();
+            if (total as f64) < full / 2.0 { continue; }
+            // Ties go to the lower atom id so the result is deterministic
+            if let Some((&atom, _)) = atoms.iter().max_by_key(|&(&a, &n)| (n, std::cmp::Reverse(a))) {
+                out.set(x, y, z, atom);
+            }
+        }
+        out
+    }
+
+    /// A copy resized by `factor` with nearest-neighbour sampling.
+    pub fn scaled(&self, factor: f64) -> VoxelGrid {
+        let size = |n: u32| ((n as f64 * factor).round() as u32).max(1);
+        let mut out = VoxelGrid::new(size(self.width), size(self.height), size(self.depth));
+        for z in 0..out.depth {
+            for y in 0..out.height {
+                for x in 0..out.width {
+                    let src = |n: u32, max: u32| (((n as f64 + 0.5) / factor) as u32).min(max - 1) as i32;
+                    let atom = self.get(src(x, self.width), src(y, self.height), src(z, self.depth));
+                    if atom != 0 {
+                        out.set(x as i32, y as i32, z as i32, atom);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// The filled voxels split into connected pieces, each listed from its
+    /// first voxel in storage order.  Voxels sharing a face are connected;
+    /// with `diagonal`, so are those sharing an edge or a corner.
+    pub fn components(&self, diagonal: bool) -> Vec<Vec<(i32, i32, i32)>> {
+        let steps: Vec<(i32, i32, i32)> = (-1..=1)
+            .flat_map(|dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| (dx, dy, dz))))
+            .filter(|&(dx, dy, dz): &(i32, i32, i32)| {
+                let moved = dx.abs() + dy.abs() + dz.abs();
+                moved == 1 || (diagonal && moved > 1)
+            })
+            .collect();
+
+        let mut seen = vec![false; self.data.len()];
+        let mut pieces = Vec::new();
+        for (x, y, z, _) in self.iter_filled() {
+            if seen[self.index(x, y, z)] { continue; }
+            seen[self.index(x, y, z)] = true;
+            let mut piece = Vec::new();
+            let mut stack = vec![(x as i32, y as i32, z as i32)];
+            while let Some((cx, cy, cz)) = stack.pop() {
+                piece.push((cx, cy, cz));
+                for &(dx, dy, dz) in &steps {
+                    let (nx, ny, nz) = (cx + dx, cy + dy, cz + dz);
+                    if self.get(nx, ny, nz) == 0 { continue; }
+                    let idx = self.index(nx as u32, ny as u32, nz as u32);
+                    if !seen[idx] {
+                        seen[idx] = true;
+                        stack.push((nx, ny, nz));
+                    }
+                }
+            }
+            pieces.push(piece);
+        }
+        pieces
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(grid: &VoxelGrid) -> Vec<(u32, u32, u32, u16)> {
+        let mut voxels: Vec<_> = grid.iter_filled().collect();
+        voxels.sort();
+        voxels
+    }
+
+    #[test]
+    fn components_split_on_faces_unless_diagonal() {
+        let mut grid = VoxelGrid::new(3, 3, 3);
+        grid.set(0, 0, 0, 1);
+        grid.set(1, 0, 0, 1);
+        grid.set(2, 1, 0, 1);    // touches (1, 0, 0) along an edge
+        grid.set(2, 2, 2, 1);    // touches nothing
+        let sizes = |diagonal| {
+            let mut sizes: Vec<usize> = grid.components(diagonal).iter().map(Vec::len).collect();
+            sizes.sort();
+            sizes
+        };
+        assert_eq!(sizes(false), [1, 1, 2]);
+        assert_eq!(sizes(true), [1, 3]);
+    }
+
+    #[test]
+    fn downsampled_keeps_cells_at_least_half_full() {
+        let mut grid = VoxelGrid::new(4, 2, 2);
+        // Left cell: 5 of 8 voxels, mostly atom 2
+        for (x, y, z) in [(0, 0, 0), (1, 0, 0), (0, 1, 0), (1, 1, 0)] { grid.set(x, y, z, 2); }
+        grid.set(0, 0, 1, 3);
+        // Right cell: 3 of 8 voxels
+        for (x, y, z) in [(2, 0, 0), (3, 0, 0), (2, 1, 0)] { grid.set(x, y, z, 2); }
+
+        let small = grid.downsampled(0.5);
+        assert_eq!(small.dims(), (2, 1, 1));
+        assert_eq!(filled(&small), [(0, 0, 0, 2)]);
+    }
+
+    #[test]
+    fn scaled_up_replicates_each_voxel() {
+        let mut grid = VoxelGrid::new(2, 1, 1);
+        grid.set(0, 0, 0, 2);
+        grid.set(1, 0, 0, 3);
+
+        // Each voxel becomes a 2×2×2 block of the same atom
+        let big = grid.scaled(2.0);
+        assert_eq!(big.dims(), (4, 2, 2));
+        let voxels = filled(&big);
+        assert_eq!(voxels.len(), 16);
+        assert!(voxels.iter().all(|&(x, _, _, atom)| atom == if x < 2 { 2 } else { 3 }), "{voxels:?}");
+    }
+
+    #[test]
+    fn rotated_turns_the_extent_and_the_voxels() {
+        let mut grid = VoxelGrid::new(2, 3, 4);
+        grid.set(1, 0, 0, 5);
+        grid.set(0, 2, 3, 6);
+
+        // A quarter-turn about y swaps width and depth: (x, z) → (z, -x)
+        let turned = grid.rotated(Axis::Y, 1);
+        assert_eq!(turned.dims(), (4, 3, 2));
+        assert_eq!(filled(&turned), [(0, 0, 0, 5), (3, 2, 1, 6)]);
+
+        // Four quarter-turns change nothing, and a turn back undoes one
+        assert_eq!(filled(&grid.rotated(Axis::X, 4)), filled(&grid));
+        assert_eq!(filled(&turned.rotated(Axis::Y, -1)), filled(&grid));
+        assert_eq!(grid.rotated(Axis::X, 1).dims(), (2, 4, 3));
+    }
+}